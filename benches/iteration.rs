@@ -1,34 +1,80 @@
+use std::convert::TryFrom;
+
 use anyhow::Error;
 use iai::{black_box, main};
 
 use mandelbrot::complex::Complex;
+use mandelbrot::double_double::DoubleDouble;
+use mandelbrot::fix::fix2x29::Fix2x29;
 use mandelbrot::fix::fix2x61::Fix2x61;
+use mandelbrot::fix::fixn::FixN;
+use mandelbrot::number::MandelbrotNumber;
 use mandelbrot::point::Point;
 use mandelbrot::set::Set;
 
 fn iai_benchmark_zero_five_times() -> Result<Point, Error> {
-    let mut p: Point = black_box(Point::ORIGIN);
+    let mut p: Point = black_box(Point::origin());
     p.iterate_n(5)?;
     Ok(p)
 }
 
 fn iai_benchmark_zero() -> Result<Point, Error> {
-    let mut p: Point = black_box(Point::ORIGIN);
+    let mut p: Point = black_box(Point::origin());
     p.iterate()?;
     Ok(p)
 }
 
-fn iai_benchmark_full_set_render() -> Result<Set, Error> {
-    let centre = Complex::new(Fix2x61::zero(), Fix2x61::zero());
-    let radius = Fix2x61::two();
+// Inside the main cardioid, where the orbit settles down to a fixed point.
+fn iai_benchmark_interior_point() -> Result<Point, Error> {
+    let mut p: Point = black_box(Point::from_parts(
+        &Fix2x61::try_from(-0.1)?,
+        &Fix2x61::try_from(0.1)?,
+    ));
+    p.iterate_n(10_000)?;
+    Ok(p)
+}
+
+fn full_set_render<N: MandelbrotNumber>() -> Result<Set<N>, Error> {
+    let centre = Complex::<N>::zero();
+    let radius = N::TWO;
 
     let mut set = Set::create(7, black_box(centre), black_box(radius))?;
     set.iterate_as_required(500, false)?;
     Ok(set)
 }
 
+fn iai_benchmark_full_set_render() -> Result<Set, Error> {
+    full_set_render::<Fix2x61>()
+}
+
+fn iai_benchmark_full_set_render_f32() -> Result<Set<f32>, Error> {
+    full_set_render()
+}
+
+fn iai_benchmark_full_set_render_f64() -> Result<Set<f64>, Error> {
+    full_set_render()
+}
+
+fn iai_benchmark_full_set_render_double_double() -> Result<Set<DoubleDouble>, Error> {
+    full_set_render()
+}
+
+fn iai_benchmark_full_set_render_fixn2() -> Result<Set<FixN<2>>, Error> {
+    full_set_render()
+}
+
+fn iai_benchmark_full_set_render_fix2x29() -> Result<Set<Fix2x29>, Error> {
+    full_set_render()
+}
+
 main!(
     iai_benchmark_zero,
     iai_benchmark_zero_five_times,
-    iai_benchmark_full_set_render
+    iai_benchmark_interior_point,
+    iai_benchmark_full_set_render,
+    iai_benchmark_full_set_render_f32,
+    iai_benchmark_full_set_render_f64,
+    iai_benchmark_full_set_render_double_double,
+    iai_benchmark_full_set_render_fixn2,
+    iai_benchmark_full_set_render_fix2x29
 );
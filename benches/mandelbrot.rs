@@ -2,32 +2,70 @@ use anyhow::{Context, Error};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 use mandelbrot::complex::{Complex, FixResult};
+use mandelbrot::double_double::DoubleDouble;
+use mandelbrot::fix::fix2x29::Fix2x29;
 use mandelbrot::fix::fix2x61::Fix2x61;
+use mandelbrot::fix::fixn::FixN;
+use mandelbrot::number::MandelbrotNumber;
+use mandelbrot::promoting::PromotingSet;
 use mandelbrot::set::Set;
 
-fn bench_render(c: &mut Criterion) {
-    let centre = Complex::new(Fix2x61::zero(), Fix2x61::zero());
-    let radius = Fix2x61::two();
+fn bench_render_with<N: MandelbrotNumber>(c: &mut Criterion, name: &str) {
+    let centre = Complex::<N>::zero();
+    let radius = N::TWO;
 
-    c.bench_function("128x128 over 20", |b| {
-        b.iter_with_large_drop(|| -> Result<Set, Error> {
-            let mut set =
-                Set::create(7, black_box(centre), black_box(radius)).context("Creating the set")?;
+    c.bench_function(name, |b| {
+        b.iter_with_large_drop(|| -> Result<Set<N>, Error> {
+            let mut set = Set::create(7, black_box(centre.clone()), black_box(radius.clone()))
+                .context("Creating the set")?;
             set.iterate_as_required(400, false)?;
             Ok(set)
         })
     });
 }
 
-fn bench_iterate(c: &mut Criterion) {
-    let zero = Complex::new(Fix2x61::zero(), Fix2x61::zero());
-    let mut z1 = black_box(zero);
+fn bench_iterate_with<N: MandelbrotNumber>(c: &mut Criterion, name: &str) {
+    let zero = Complex::<N>::zero();
+    let mut z1 = black_box(zero.clone());
     let z2 = black_box(zero);
 
-    c.bench_function("iterate zero", |b| {
-        b.iter(|| -> FixResult<Complex> { z1.iterate_mandelbrot(&z2).map(|_| z1) })
+    c.bench_function(name, |b| {
+        b.iter(|| -> FixResult<Complex<N>> { z1.iterate_mandelbrot(&z2).map(|_| z1.clone()) })
     });
 }
 
-criterion_group!(benches, bench_render, bench_iterate);
+fn bench_render(c: &mut Criterion) {
+    bench_render_with::<Fix2x61>(c, "128x128 over 20");
+    bench_render_with::<f32>(c, "128x128 over 20 f32");
+    bench_render_with::<f64>(c, "128x128 over 20 f64");
+    bench_render_with::<DoubleDouble>(c, "128x128 over 20 DoubleDouble");
+    bench_render_with::<FixN<2>>(c, "128x128 over 20 FixN<2>");
+    bench_render_with::<Fix2x29>(c, "128x128 over 20 Fix2x29");
+}
+
+// The default render, letting the set pick f64 over fixed point for itself.
+fn bench_promoting_render(c: &mut Criterion) {
+    let centre = Complex::zero();
+    let radius = Fix2x61::two();
+
+    c.bench_function("128x128 over 20 promoting", |b| {
+        b.iter_with_large_drop(|| -> Result<PromotingSet, Error> {
+            let mut set = PromotingSet::create(7, black_box(centre), black_box(radius))
+                .context("Creating the set")?;
+            set.iterate_as_required(400, false)?;
+            Ok(set)
+        })
+    });
+}
+
+fn bench_iterate(c: &mut Criterion) {
+    bench_iterate_with::<Fix2x61>(c, "iterate zero");
+    bench_iterate_with::<f32>(c, "iterate zero f32");
+    bench_iterate_with::<f64>(c, "iterate zero f64");
+    bench_iterate_with::<DoubleDouble>(c, "iterate zero DoubleDouble");
+    bench_iterate_with::<FixN<2>>(c, "iterate zero FixN<2>");
+    bench_iterate_with::<Fix2x29>(c, "iterate zero Fix2x29");
+}
+
+criterion_group!(benches, bench_render, bench_promoting_render, bench_iterate);
 criterion_main!(benches);
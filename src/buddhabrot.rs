@@ -0,0 +1,295 @@
+use std::ops::Range;
+
+use anyhow::{bail, Error};
+use rayon::prelude::*;
+
+use crate::colours::save_png;
+use crate::complex::{Complex, FixError};
+use crate::fix::fix2x61::Fix2x61;
+use crate::number::MandelbrotNumber;
+use crate::set::{known_period, quad_view, Orientation};
+use crate::zoom_path::Quad;
+
+// Rather than colouring each point by how long it takes to escape, a Buddhabrot counts how often
+// the orbits of escaping points pass through each pixel.  A Nebulabrot does the same for three
+// iteration limits at once, one for each of red, green and blue.
+pub struct Buddhabrot<N = Fix2x61> {
+    power_size: usize,
+    centre: Complex<N>,
+    radius: N,
+    orientation: Orientation,
+    // Orbits count towards a channel if they escape within its limit.
+    limits: Vec<u64>,
+    // How many values of c we've tried so far, so that more samples carry on from there.
+    samples: u64,
+    histograms: Vec<Vec<u32>>,
+}
+
+// Where orbits land in the picture.  Orbits are only tracked to the nearest pixel, so f64 is
+// plenty even when we iterate in something more precise.
+struct View {
+    left: f64,
+    top: f64,
+    scale: f64,
+    size: usize,
+}
+
+impl View {
+    fn pixel<N: MandelbrotNumber>(&self, z: &Complex<N>) -> Option<usize> {
+        let x = (z.r.to_f64() - self.left) * self.scale;
+        let y = (z.i.to_f64() - self.top) * self.scale;
+        let in_range = |v: f64| v >= 0.0 && v < self.size as f64;
+        if in_range(x) && in_range(y) {
+            Some(x as usize + self.size * y as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl<N: MandelbrotNumber> Buddhabrot<N> {
+    pub fn create(
+        power_size: usize,
+        centre: Complex<N>,
+        radius: N,
+        limits: Vec<u64>,
+    ) -> Result<Buddhabrot<N>, Error> {
+        if limits.len() != 1 && limits.len() != 3 {
+            bail!(
+                "Need one iteration limit for greyscale or three for RGB, not {}",
+                limits.len()
+            )
+        }
+        if limits.contains(&0) {
+            bail!("Iteration limits must be at least 1")
+        }
+        let size = 1 << power_size;
+        Ok(Buddhabrot {
+            power_size,
+            centre,
+            radius,
+            orientation: Orientation::default(),
+            histograms: vec![vec![0; size * size]; limits.len()],
+            limits,
+            samples: 0,
+        })
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    // Zooms in to a quarter of the view, as Set::subset does.  Nothing we've counted so far is
+    // kept.
+    pub fn subset(&self, quad: &Quad) -> Result<Buddhabrot<N>, Error> {
+        let quad = self.orientation.stored_quad(quad);
+        let (centre, radius) = quad_view(self.centre.clone(), self.radius.clone(), &quad)?;
+        let mut subset = Buddhabrot::create(self.power_size, centre, radius, self.limits.clone())?;
+        subset.orientation = self.orientation;
+        Ok(subset)
+    }
+
+    pub fn size(&self) -> usize {
+        1 << self.power_size
+    }
+
+    pub fn histogram(&self, channel: usize) -> &[u32] {
+        &self.histograms[channel]
+    }
+
+    fn view(&self) -> View {
+        let radius = self.radius.to_f64();
+        View {
+            left: self.centre.r.to_f64() - radius,
+            top: self.centre.i.to_f64() - radius,
+            scale: self.size() as f64 / (2.0 * radius),
+            size: self.size(),
+        }
+    }
+
+    // Tries this many more values of c, spread over everywhere an orbit might start rather than
+    // just the view, and returns how many of them escaped.
+    pub fn sample(&mut self, samples: u64) -> Result<u64, Error> {
+        let view = self.view();
+        let start = self.samples;
+        let chunks = rayon::current_num_threads() as u64 * 4;
+        let chunk_size = samples.div_ceil(chunks).max(1);
+        let empty = || (0, vec![vec![0; view.size * view.size]; self.limits.len()]);
+        let (escaped, histograms) = (0..samples.div_ceil(chunk_size))
+            .into_par_iter()
+            .map(|chunk| {
+                let from = start + chunk * chunk_size;
+                self.accumulate(&view, from..(from + chunk_size).min(start + samples))
+            })
+            .try_reduce(empty, |(a, mut total), (b, histograms)| {
+                for (total, histogram) in total.iter_mut().zip(histograms) {
+                    total.iter_mut().zip(histogram).for_each(|(t, h)| *t += h);
+                }
+                Ok((a + b, total))
+            })?;
+
+        for (total, histogram) in self.histograms.iter_mut().zip(histograms) {
+            total.iter_mut().zip(histogram).for_each(|(t, h)| *t += h);
+        }
+        self.samples += samples;
+        Ok(escaped)
+    }
+
+    fn accumulate(
+        &self,
+        view: &View,
+        samples: Range<u64>,
+    ) -> Result<(u64, Vec<Vec<u32>>), FixError> {
+        let max_limit = *self.limits.iter().max().unwrap();
+        let mut histograms = vec![vec![0; view.size * view.size]; self.limits.len()];
+        let mut escaped = 0;
+        let mut orbit = vec![];
+        for sample in samples {
+            let (r, i) = (
+                4.0 * unit(2 * sample) - 2.0,
+                4.0 * unit(2 * sample + 1) - 2.0,
+            );
+            // The main cardioid and the period two bulb are most of the set, and nothing in them
+            // escapes, so there's no point iterating them.
+            if r * r + i * i >= 4.0 || known_period(&Complex::new(r, i)).is_some() {
+                continue;
+            }
+            let c = Complex::new(N::from_f64(r)?, N::from_f64(i)?);
+
+            // Starting from c, like Point.
+            let mut z = c.clone();
+            orbit.clear();
+            orbit.extend(view.pixel(&z));
+            let mut iterations = None;
+            for n in 1..=max_limit {
+                match z.iterate_mandelbrot(&c) {
+                    Ok(()) => orbit.extend(view.pixel(&z)),
+                    Err(FixError::Escaped) => {
+                        iterations = Some(n);
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if let Some(iterations) = iterations {
+                escaped += 1;
+                for (limit, histogram) in self.limits.iter().zip(histograms.iter_mut()) {
+                    if iterations <= *limit {
+                        orbit.iter().for_each(|&p| histogram[p] += 1);
+                    }
+                }
+            }
+        }
+        Ok((escaped, histograms))
+    }
+
+    // Each channel scaled so its busiest pixel is at full brightness, with a square root to bring
+    // out the fainter orbits.  Interleaved as the PNG wants them.
+    pub fn bytes(&self) -> Vec<u8> {
+        let size = self.size();
+        let tone_mapped: Vec<Vec<u8>> = self
+            .histograms
+            .iter()
+            .map(|histogram| {
+                let max = histogram.iter().copied().max().unwrap_or_default().max(1) as f64;
+                histogram
+                    .iter()
+                    .map(|&count| ((count as f64 / max).sqrt() * 255.0).round() as u8)
+                    .collect()
+            })
+            .collect();
+        let rows: Vec<usize> = match self.orientation {
+            Orientation::ImaginaryDown => (0..size).collect(),
+            Orientation::ImaginaryUp => (0..size).rev().collect(),
+        };
+        rows.into_iter()
+            .flat_map(|y| y * size..(y + 1) * size)
+            .flat_map(|p| tone_mapped.iter().map(move |channel| channel[p]))
+            .collect()
+    }
+
+    pub fn render_to_file(&self, filename: &str) -> std::io::Result<()> {
+        let colour_type = match self.histograms.len() {
+            1 => image::ColorType::Gray(8),
+            _ => image::ColorType::RGB(8),
+        };
+        save_png(filename, &self.bytes(), self.size() as u32, colour_type)
+    }
+}
+
+// A number in [0, 1) for each index, from the SplitMix64 generator.  Hashing the index rather
+// than keeping state means samples come out the same however they're split between threads.
+pub(crate) fn unit(index: u64) -> f64 {
+    (split_mix(index) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// The SplitMix64 output for the state index * gamma.
+pub(crate) fn split_mix(index: u64) -> u64 {
+    let mut x = index.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::fix::fix2x61::Fix2x61;
+
+    use super::Buddhabrot;
+
+    fn nebulabrot() -> Result<Buddhabrot, Error> {
+        Buddhabrot::create(6, Complex::zero(), Fix2x61::try_from(2)?, vec![200, 50, 20])
+    }
+
+    #[test]
+    fn longer_limits_see_more() -> Result<(), Error> {
+        let mut set = nebulabrot()?;
+        assert!(set.sample(20_000)? > 1000);
+        for channel in 0..2 {
+            let (more, fewer) = (set.histogram(channel), set.histogram(channel + 1));
+            assert!(more.iter().zip(fewer).all(|(m, f)| m >= f));
+            assert!(more.iter().sum::<u32>() > fewer.iter().sum::<u32>());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sampling_carries_on() -> Result<(), Error> {
+        let (mut once, mut twice) = (nebulabrot()?, nebulabrot()?);
+        let escaped = once.sample(10_000)?;
+        assert_eq!(twice.sample(3_000)? + twice.sample(7_000)?, escaped);
+        for channel in 0..3 {
+            assert_eq!(once.histogram(channel), twice.histogram(channel));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn symmetric_about_the_real_axis() -> Result<(), Error> {
+        let mut set = Buddhabrot::create(6, Complex::zero(), Fix2x61::try_from(2)?, vec![100])?;
+        set.sample(50_000)?;
+        let size = set.size();
+        let half = |rows: std::ops::Range<usize>| -> u32 {
+            rows.map(|y| {
+                set.histogram(0)[y * size..(y + 1) * size]
+                    .iter()
+                    .sum::<u32>()
+            })
+            .sum()
+        };
+        let (top, bottom) = (half(0..size / 2) as f64, half(size / 2..size) as f64);
+        assert!((top / bottom - 1.0).abs() < 0.05, "{} vs {}", top, bottom);
+
+        let bytes = set.bytes();
+        assert_eq!(bytes.len(), size * size);
+        assert_eq!(bytes.iter().max(), Some(&255));
+        assert!(Buddhabrot::create(6, Complex::zero(), Fix2x61::try_from(2)?, vec![1, 2]).is_err());
+        Ok(())
+    }
+}
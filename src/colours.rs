@@ -1,4 +1,6 @@
-use crate::set::Set;
+use crate::number::MandelbrotNumber;
+use crate::point::Point;
+use crate::set::{Orientation, Set};
 use lazy_static::lazy_static;
 use std::path::Path;
 use std::str::FromStr;
@@ -45,6 +47,8 @@ enum_deref_trait! {
         Greyscale,
         Fractint,
         LogGreyscale,
+        Roots,
+        Distance,
     }
 }
 
@@ -52,6 +56,14 @@ pub trait ColourSchemeT {
     fn colour_type(&self) -> image::ColorType;
     fn bytes(&self, iterations: u64) -> Vec<u8>;
     fn escaped_bytes(&self) -> Vec<u8>;
+    // For points that settled on an attractor rather than escaping.
+    fn converged_bytes(&self, _attractor: u8, iterations: u64) -> Vec<u8> {
+        self.bytes(iterations)
+    }
+    // For escaped points with an estimate of how far they are from the set, in pixels.
+    fn distance_bytes(&self, _distance: f64, iterations: u64) -> Vec<u8> {
+        self.bytes(iterations)
+    }
 }
 
 impl ColourSchemeT for Greyscale {
@@ -97,6 +109,62 @@ impl ColourSchemeT for LogGreyscale {
     }
 }
 
+// Each attractor gets its own hue, darkening the longer points took to get there.  Anything that
+// escaped is grey.
+impl ColourSchemeT for Roots {
+    fn colour_type(&self) -> ColorType {
+        image::ColorType::RGB(8)
+    }
+
+    fn bytes(&self, iterations: u64) -> Vec<u8> {
+        let grey = Greyscale.bytes(iterations)[0] / 2 + 64;
+        vec![grey, grey, grey]
+    }
+
+    fn escaped_bytes(&self) -> Vec<u8> {
+        vec![0, 0, 0]
+    }
+
+    fn converged_bytes(&self, attractor: u8, iterations: u64) -> Vec<u8> {
+        // Stepping round by the golden ratio keeps neighbouring attractors well apart.
+        let hue = (attractor as f64 * 0.618_033_988_75).fract() * 6.0;
+        let value = 0.25 + 0.75 * 0.92f64.powf(iterations as f64);
+        let x = 1.0 - (hue % 2.0 - 1.0).abs();
+        let (r, g, b) = match hue as u8 {
+            0 => (1.0, x, 0.0),
+            1 => (x, 1.0, 0.0),
+            2 => (0.0, 1.0, x),
+            3 => (0.0, x, 1.0),
+            4 => (x, 0.0, 1.0),
+            _ => (1.0, 0.0, x),
+        };
+        [r, g, b]
+            .iter()
+            .map(|c| (c * value * 255.0).round() as u8)
+            .collect()
+    }
+}
+
+// Dark close to the set and brightening over the next few pixels, so that filaments too thin for
+// any point to land on still show up.
+impl ColourSchemeT for Distance {
+    fn colour_type(&self) -> ColorType {
+        image::ColorType::Gray(8)
+    }
+
+    fn bytes(&self, _iterations: u64) -> Vec<u8> {
+        vec![255]
+    }
+
+    fn escaped_bytes(&self) -> Vec<u8> {
+        vec![0]
+    }
+
+    fn distance_bytes(&self, distance: f64, _iterations: u64) -> Vec<u8> {
+        vec![((distance / 2.0).tanh() * 255.0).round() as u8]
+    }
+}
+
 impl FromStr for ColourScheme {
     type Err = Error;
 
@@ -105,18 +173,30 @@ impl FromStr for ColourScheme {
             "fractint" => ColourScheme::Fractint,
             "grey" => ColourScheme::Greyscale,
             "loggrey" => ColourScheme::LogGreyscale,
+            "roots" => ColourScheme::Roots,
+            "distance" => ColourScheme::Distance,
             _ => bail!("Invalid colour scheme"),
         })
     }
 }
 
-impl Set {
+impl<N: MandelbrotNumber> Set<N> {
     pub fn render_to_file(&self, scheme: &ColourScheme, filename: &str) -> std::io::Result<()> {
-        let buffer: Vec<u8> = self
-            .points
-            .iter()
+        let size = self.size() as usize;
+        let pixel = 2.0 * self.radius.to_f64() / size as f64;
+        let rows: Vec<&[Point<N>]> = match self.orientation {
+            Orientation::ImaginaryDown => self.points.chunks(size).collect(),
+            Orientation::ImaginaryUp => self.points.chunks(size).rev().collect(),
+        };
+        let buffer: Vec<u8> = rows
+            .into_iter()
+            .flatten()
             .flat_map(|p| {
-                if p.escaped {
+                if let Some(attractor) = p.attractor {
+                    scheme.converged_bytes(attractor, p.iterations)
+                } else if let (true, Some(distance)) = (p.escaped, p.distance()) {
+                    scheme.distance_bytes(distance / pixel, p.iterations)
+                } else if p.escaped {
                     scheme.bytes(p.iterations)
                 } else {
                     scheme.escaped_bytes()
@@ -124,16 +204,20 @@ impl Set {
             })
             .collect();
 
-        image::save_buffer(
-            &Path::new(filename),
-            &buffer[..],
-            self.size(),
-            self.size(),
-            scheme.colour_type(),
-        )
+        save_png(filename, &buffer, self.size(), scheme.colour_type())
     }
 }
 
+// Every renderer's pictures are square.
+pub(crate) fn save_png(
+    filename: &str,
+    buffer: &[u8],
+    size: u32,
+    colour_type: ColorType,
+) -> std::io::Result<()> {
+    image::save_buffer(Path::new(filename), buffer, size, size, colour_type)
+}
+
 lazy_static! {
     // Ref https://svn.fractint.net/trunk/fractint/maps/default.map
     pub static ref VGA_MAP: Vec<Rgb> = vec!(
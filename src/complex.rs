@@ -1,12 +1,13 @@
-use std::convert::TryInto;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
 use std::str::FromStr;
 
-use anyhow::bail;
-use num::abs;
+use anyhow::{bail, Context};
 use thiserror::Error;
 
 use crate::fix::fix2x61::Fix2x61;
-use crate::fix::fix4x123::Fix4x123;
+use crate::number::{MandelbrotNumber, WideNumber};
 
 #[derive(Clone, Debug, Error)]
 pub enum FixError {
@@ -16,23 +17,39 @@ pub enum FixError {
     Underflow { op: &'static str },
     #[error("Iteration triggered escape")]
     Escaped,
+    #[error("Iteration converged on attractor {attractor}")]
+    Converged { attractor: u8 },
 }
 
 pub type FixResult<T> = Result<T, FixError>;
 
+// Magnet orbits that get this far from the origin never come back.
+const MAGNET_ESCAPE_RADIUS: f64 = 100.0;
+
 #[derive(Copy, Debug, Clone, PartialEq, Eq)]
-pub struct Complex {
-    pub r: Fix2x61,
-    pub i: Fix2x61,
+pub struct Complex<N = Fix2x61> {
+    pub r: N,
+    pub i: N,
 }
 
-impl Complex {
-    pub const fn zero() -> Complex {
+impl<N: MandelbrotNumber> Complex<N> {
+    pub const fn zero() -> Complex<N> {
         Complex {
-            r: Fix2x61::zero(),
-            i: Fix2x61::zero(),
+            r: N::ZERO,
+            i: N::ZERO,
         }
     }
+
+    pub fn convert<M>(&self) -> Result<Complex<M>, M::Error>
+    where
+        M: MandelbrotNumber + TryFrom<N>,
+    {
+        self.convert_with(|n| M::try_from(n.clone()))
+    }
+
+    pub fn convert_with<M, E>(&self, number: impl Fn(&N) -> Result<M, E>) -> Result<Complex<M>, E> {
+        Ok(Complex::new(number(&self.r)?, number(&self.i)?))
+    }
 }
 
 impl FromStr for Complex {
@@ -44,14 +61,21 @@ impl FromStr for Complex {
             bail!("Must supply a complex in the form '0.0,0.0'")
         }
 
-        let r = f64::from_str(split[0])?;
-        let i = f64::from_str(split[1])?;
+        let r = Fix2x61::from_str(split[0]).context("Parsing the real part")?;
+        let i = Fix2x61::from_str(split[1]).context("Parsing the imaginary part")?;
 
-        Ok(Complex::new(r.try_into()?, i.try_into()?))
+        Ok(Complex::new(r, i))
     }
 }
 
-const fn overflow_escapes(e: FixError) -> FixError {
+// The same form that FromStr accepts, so exact types round-trip.
+impl<N: Display> Display for Complex<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.r, self.i)
+    }
+}
+
+pub(crate) const fn overflow_escapes(e: FixError) -> FixError {
     if let FixError::Overflow { op: _ } = e {
         FixError::Escaped
     } else {
@@ -59,21 +83,30 @@ const fn overflow_escapes(e: FixError) -> FixError {
     }
 }
 
-impl Complex {
-    pub const fn new(r: Fix2x61, i: Fix2x61) -> Complex {
+impl<N> Complex<N> {
+    pub const fn new(r: N, i: N) -> Complex<N> {
         Complex { r, i }
     }
+}
 
+impl<N: MandelbrotNumber> Complex<N> {
     #[inline(always)] // Microbenchmarks suggest inlining slows down single iterations but speeds up full renders
-    pub fn iterate_mandelbrot(&mut self, loc: &Complex) -> FixResult<()> {
-        let r = self.r;
-        let i = self.i;
+    pub fn iterate_mandelbrot(&mut self, loc: &Complex<N>) -> FixResult<()> {
+        let r = self.r.clone();
+        let i = self.i.clone();
 
         // Square
-        let (r, i) = ((r * r - i * i)?, (r * i + r * i)?);
+        let ri = r.clone().widening_mul(i.clone());
+        let (r, i) = (
+            r.widening_square().try_sub(i.widening_square())?,
+            ri.clone().try_add(ri)?,
+        );
 
         // Add
-        let (r, i) = ((r + From::from(loc.r))?, (i + From::from(loc.i))?);
+        let (r, i) = (
+            r.try_add(loc.r.clone().widen())?,
+            i.try_add(loc.i.clone().widen())?,
+        );
 
         // Truncate
         let (r, i) = (
@@ -82,7 +115,11 @@ impl Complex {
         );
 
         // Escape check
-        if abs((r * r + i * i).map_err(overflow_escapes)?.0) >= Fix4x123::four().0 {
+        let norm_sqr = r
+            .clone()
+            .widening_square()
+            .try_add(i.clone().widening_square());
+        if norm_sqr.map_err(overflow_escapes)?.exceeds_four() {
             Err(FixError::Escaped)
         } else {
             self.r = r;
@@ -92,17 +129,421 @@ impl Complex {
     }
 }
 
-impl Default for Complex {
+impl<N: MandelbrotNumber> Complex<N> {
+    // z^exponent + c.  Orbits escape once |z| passes 2^(1/(exponent - 1)), which we check as
+    // |z|^(2 * (exponent - 1)) > 4 in f64, with a little slack for its rounding.  The power is
+    // truncated at each step of the chain, so unlike iterate_mandelbrot it isn't exact until the
+    // addition.
+    pub fn iterate_multibrot(&mut self, loc: &Complex<N>, exponent: u32) -> FixResult<()> {
+        // Overflow anywhere in the power means |z^exponent| is at least 4, which is far enough.
+        let z = self.pow(exponent).map_err(overflow_escapes)?;
+        let z = (z + loc.clone()).map_err(overflow_escapes)?;
+
+        let (r, i) = (z.r.to_f64(), z.i.to_f64());
+        let escaped = (r * r + i * i).powi(exponent as i32 - 1) > 4.0 * (1.0 + 1e-9);
+        if escaped || z.norm_sqr().map_err(overflow_escapes)?.exceeds_four() {
+            Err(FixError::Escaped)
+        } else {
+            *self = z;
+            Ok(())
+        }
+    }
+
+    // Squares for every bit of the exponent after the first, multiplying in self for each bit
+    // that's set.
+    pub fn pow(&self, exponent: u32) -> FixResult<Self> {
+        if exponent == 0 {
+            return Ok(Complex::one());
+        }
+        let mut result = self.clone();
+        for bit in (0..31 - exponent.leading_zeros()).rev() {
+            result = result.square()?;
+            if (exponent >> bit) & 1 == 1 {
+                result = (result * self.clone())?;
+            }
+        }
+        Ok(result)
+    }
+}
+
+// Variations on z^2 + c that take absolute values or conjugates of parts of z.  Squaring doesn't
+// care about signs, so each one only changes the sign of part of the square.
+impl<N: MandelbrotNumber> Complex<N> {
+    // z = (|Re z| + i|Im z|)^2 + c
+    pub fn iterate_burning_ship(&mut self, loc: &Complex<N>) -> FixResult<()> {
+        let ri = abs::<N>(self.r.clone().widening_mul(self.i.clone()))?;
+        let r = self
+            .r
+            .clone()
+            .widening_square()
+            .try_sub(self.i.clone().widening_square())?;
+        self.add_and_check(r, ri.clone().try_add(ri)?, loc)
+    }
+
+    // z = conj(z)^2 + c, also known as the Mandelbar set.
+    pub fn iterate_tricorn(&mut self, loc: &Complex<N>) -> FixResult<()> {
+        let ri = self.r.clone().widening_mul(self.i.clone());
+        let r = self
+            .r
+            .clone()
+            .widening_square()
+            .try_sub(self.i.clone().widening_square())?;
+        self.add_and_check(r, negate::<N>(ri.clone().try_add(ri)?)?, loc)
+    }
+
+    // z = (|Re z| - i Im z)^2 + c
+    pub fn iterate_perpendicular(&mut self, loc: &Complex<N>) -> FixResult<()> {
+        let ri = self.r.clone().widening_mul(self.i.clone());
+        let ri = if self.r.clone().widen().is_negative() {
+            ri
+        } else {
+            negate::<N>(ri)?
+        };
+        let r = self
+            .r
+            .clone()
+            .widening_square()
+            .try_sub(self.i.clone().widening_square())?;
+        self.add_and_check(r, ri.clone().try_add(ri)?, loc)
+    }
+
+    // z = |Re(z^2)| + i Im(z^2) + c
+    pub fn iterate_celtic(&mut self, loc: &Complex<N>) -> FixResult<()> {
+        let ri = self.r.clone().widening_mul(self.i.clone());
+        let r = abs::<N>(
+            self.r
+                .clone()
+                .widening_square()
+                .try_sub(self.i.clone().widening_square())?,
+        )?;
+        self.add_and_check(r, ri.clone().try_add(ri)?, loc)
+    }
+
+    // The rest of iterate_mandelbrot, given a square that's still at full width.
+    fn add_and_check(&mut self, r: N::Wide, i: N::Wide, loc: &Complex<N>) -> FixResult<()> {
+        let (r, i) = (
+            r.try_add(loc.r.clone().widen())?,
+            i.try_add(loc.i.clone().widen())?,
+        );
+        let (r, i) = (
+            r.truncate().map_err(overflow_escapes)?,
+            i.truncate().map_err(overflow_escapes)?,
+        );
+        let norm_sqr = r
+            .clone()
+            .widening_square()
+            .try_add(i.clone().widening_square());
+        if norm_sqr.map_err(overflow_escapes)?.exceeds_four() {
+            Err(FixError::Escaped)
+        } else {
+            self.r = r;
+            self.i = i;
+            Ok(())
+        }
+    }
+}
+
+// Formulas that need more than the current value of z.
+impl<N: MandelbrotNumber> Complex<N> {
+    // z = z^2 + Re(c) + Im(c) * previous, where previous is the value of z before this one.
+    pub fn iterate_phoenix(
+        &mut self,
+        previous: &mut Complex<N>,
+        loc: &Complex<N>,
+    ) -> FixResult<()> {
+        let ri = self.r.clone().widening_mul(self.i.clone());
+        let r = <N as Clone>::clone(&self.r)
+            .widening_square()
+            .try_sub(self.i.clone().widening_square())?
+            .try_add(loc.i.clone().widening_mul(previous.r.clone()))?;
+        let i = ri
+            .clone()
+            .try_add(ri)?
+            .try_add(loc.i.clone().widening_mul(previous.i.clone()))?;
+        let current = self.clone();
+        self.add_and_check(r, i, &Complex::new(loc.r.clone(), N::ZERO))?;
+        *previous = current;
+        Ok(())
+    }
+
+    // z = ((z^2 + c - 1) / (2z + c - 2))^2
+    pub fn iterate_magnet_i(&mut self, loc: &Complex<N>) -> FixResult<()> {
+        let step = || {
+            let (c1, c2) = magnet_constants(loc)?;
+            let numerator = (self.clone().far_square()? + c1)?;
+            let denominator = ((self.clone() + self.clone())? + c2)?;
+            numerator.far_div(denominator)?.far_square()
+        };
+        let z = step().map_err(overflow_escapes)?;
+        self.check_magnet(z)
+    }
+
+    // z = ((z^3 + 3(c - 1)z + (c - 1)(c - 2)) / (3z^2 + 3(c - 2)z + (c - 1)(c - 2) + 1))^2
+    pub fn iterate_magnet_ii(&mut self, loc: &Complex<N>) -> FixResult<()> {
+        let three = |z: Complex<N>| (z.clone() + z.clone())? + z;
+        let step = || {
+            let (c1, c2) = magnet_constants(loc)?;
+            let c1c2 = c1.clone().far_mul(c2.clone())?;
+            // Factorised to keep the parts small.
+            let numerator = (self
+                .clone()
+                .far_mul((self.clone().far_square()? + three(c1)?)?)?
+                + c1c2.clone())?;
+            let denominator =
+                ((three(self.clone().far_mul((self.clone() + c2)?)?)? + c1c2)? + Complex::one())?;
+            numerator.far_div(denominator)?.far_square()
+        };
+        let z = step().map_err(overflow_escapes)?;
+        self.check_magnet(z)
+    }
+
+    // Magnet orbits either settle on the fixed point at 1 or head off to infinity.  Far out they
+    // only grow like |z|^2 / 4, so an orbit can pass 2, or even 4, and still come back: we wait
+    // until |z| reaches MAGNET_ESCAPE_RADIUS, checked in f64 like convergence.  Floats have the
+    // range for that, but fixed point types overflow long before, so for them anything that
+    // leaves [-4, 4) part way through a step counts as escaping, and some points that would come
+    // back are wrongly shown escaping.
+    fn check_magnet(&mut self, z: Complex<N>) -> FixResult<()> {
+        let (r, i) = (z.r.to_f64(), z.i.to_f64());
+        if r * r + i * i >= MAGNET_ESCAPE_RADIUS * MAGNET_ESCAPE_RADIUS {
+            return Err(FixError::Escaped);
+        }
+        *self = z;
+        let r = r - 1.0;
+        if r * r + i * i < 1e-12 {
+            Err(FixError::Converged { attractor: 0 })
+        } else {
+            Ok(())
+        }
+    }
+
+    // Products and quotients for orbits that go beyond [-4, 4), as far as N can follow them.
+    fn far_mul(self, rhs: Self) -> FixResult<Self> {
+        self.multiply(rhs, WideNumber::truncate_far)
+    }
+
+    fn far_square(self) -> FixResult<Self> {
+        self.clone().far_mul(self)
+    }
+
+    fn far_div(self, rhs: Self) -> FixResult<Self> {
+        self.divide(rhs, WideNumber::try_div_far, WideNumber::truncate_far)
+    }
+}
+
+// c - 1 and c - 2
+fn magnet_constants<N: MandelbrotNumber>(c: &Complex<N>) -> FixResult<(Complex<N>, Complex<N>)> {
+    let one = Complex::one();
+    let c1 = (c.clone() - one.clone())?;
+    Ok((c1.clone(), (c1 - one)?))
+}
+
+fn negate<N: MandelbrotNumber>(wide: N::Wide) -> FixResult<N::Wide> {
+    N::ZERO.widen().try_sub(wide)
+}
+
+fn abs<N: MandelbrotNumber>(wide: N::Wide) -> FixResult<N::Wide> {
+    if wide.is_negative() {
+        negate::<N>(wide)
+    } else {
+        Ok(wide)
+    }
+}
+
+// Plain f64 arithmetic without any range checks, for working with constants before they're
+// converted to the type we iterate in.
+impl Complex<f64> {
+    pub(crate) fn plus(self, rhs: Self) -> Self {
+        Complex::new(self.r + rhs.r, self.i + rhs.i)
+    }
+
+    pub(crate) fn minus(self, rhs: Self) -> Self {
+        Complex::new(self.r - rhs.r, self.i - rhs.i)
+    }
+
+    pub(crate) fn times(self, rhs: Self) -> Self {
+        Complex::new(
+            self.r * rhs.r - self.i * rhs.i,
+            self.r * rhs.i + self.i * rhs.r,
+        )
+    }
+
+    pub(crate) fn divided_by(self, rhs: Self) -> Self {
+        let q = self.times(Complex::new(rhs.r, -rhs.i));
+        let norm_sqr = rhs.magnitude_sqr();
+        Complex::new(q.r / norm_sqr, q.i / norm_sqr)
+    }
+
+    pub(crate) fn magnitude_sqr(self) -> f64 {
+        self.r * self.r + self.i * self.i
+    }
+
+    // One step of z^exponent + c along with its derivative, exponent z^(exponent - 1) dz, plus one
+    // for the derivative with respect to c.  Julia sets want it with respect to the starting
+    // value instead, which leaves out the one.
+    pub(crate) fn iterate_with_derivative(
+        self,
+        dz: Self,
+        c: Self,
+        exponent: u8,
+        with_respect_to_c: bool,
+    ) -> (Self, Self) {
+        let power = (2..exponent).fold(self, |p, _| p.times(self));
+        let dz = power.times(dz).times(Complex::new(exponent as f64, 0.0));
+        let plus = if with_respect_to_c { 1.0 } else { 0.0 };
+        (power.times(self).plus(c), dz.plus(Complex::new(plus, 0.0)))
+    }
+}
+
+impl<N: Default> Default for Complex<N> {
     fn default() -> Self {
         Complex::new(Default::default(), Default::default())
     }
 }
 
+impl<N: MandelbrotNumber> Complex<N> {
+    pub const fn one() -> Complex<N> {
+        Complex {
+            r: N::ONE,
+            i: N::ZERO,
+        }
+    }
+
+    pub const fn i() -> Complex<N> {
+        Complex {
+            r: N::ZERO,
+            i: N::ONE,
+        }
+    }
+
+    // Negating the most negative value overflows, so this can fail too.
+    pub fn conj(&self) -> FixResult<Self> {
+        Ok(Complex::new(
+            self.r.clone(),
+            N::ZERO.try_sub(self.i.clone())?,
+        ))
+    }
+
+    // Kept wide, so it's exact.
+    pub fn norm_sqr(&self) -> FixResult<N::Wide> {
+        self.r
+            .clone()
+            .widening_square()
+            .try_add(self.i.clone().widening_square())
+    }
+
+    pub fn square(&self) -> FixResult<Self> {
+        let ri = self.r.clone().widening_mul(self.i.clone());
+        Ok(Complex::new(
+            self.r
+                .clone()
+                .widening_square()
+                .try_sub(self.i.clone().widening_square())?
+                .truncate()?,
+            ri.clone().try_add(ri)?.truncate()?,
+        ))
+    }
+}
+
+impl<N: MandelbrotNumber> Neg for Complex<N> {
+    type Output = FixResult<Self>;
+
+    fn neg(self) -> Self::Output {
+        Complex::zero() - self
+    }
+}
+
+impl<N: MandelbrotNumber> Add for Complex<N> {
+    type Output = FixResult<Self>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Ok(Complex::new(self.r.try_add(rhs.r)?, self.i.try_add(rhs.i)?))
+    }
+}
+
+impl<N: MandelbrotNumber> Sub for Complex<N> {
+    type Output = FixResult<Self>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Ok(Complex::new(self.r.try_sub(rhs.r)?, self.i.try_sub(rhs.i)?))
+    }
+}
+
+// Each part of the product is summed at full width and only truncated at the end.
+impl<N: MandelbrotNumber> Mul for Complex<N> {
+    type Output = FixResult<Self>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.multiply(rhs, WideNumber::truncate)
+    }
+}
+
+// a / b = a * conj(b) / |b|^2, with everything up to the final division kept wide.  The wide
+// type doesn't always have room for those products, so if they don't fit we try again with both
+// sides halved, at the cost of their last bits.
+impl<N: MandelbrotNumber> Div for Complex<N> {
+    type Output = FixResult<Self>;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.divide(rhs, WideNumber::try_div, WideNumber::truncate)
+    }
+}
+
+// The operators, given how to get back from the wide type.
+impl<N: MandelbrotNumber> Complex<N> {
+    fn multiply(self, rhs: Self, narrow: fn(&N::Wide) -> FixResult<N>) -> FixResult<Self> {
+        let r = self
+            .r
+            .clone()
+            .widening_mul(rhs.r.clone())
+            .try_sub(self.i.clone().widening_mul(rhs.i.clone()))?;
+        let i = self
+            .r
+            .widening_mul(rhs.i)
+            .try_add(self.i.widening_mul(rhs.r))?;
+        Ok(Complex::new(narrow(&r)?, narrow(&i)?))
+    }
+
+    fn divide(
+        self,
+        rhs: Self,
+        div: fn(N::Wide, N::Wide) -> FixResult<N>,
+        narrow: fn(&N::Wide) -> FixResult<N>,
+    ) -> FixResult<Self> {
+        let quotient = |a: Self, b: Self| {
+            let norm_sqr = b.norm_sqr()?;
+            let r = (a.r.clone().widening_mul(b.r.clone()))
+                .try_add(a.i.clone().widening_mul(b.i.clone()))?;
+            let i = a.i.widening_mul(b.r).try_sub(a.r.widening_mul(b.i))?;
+            Ok(Complex::new(div(r, norm_sqr.clone())?, div(i, norm_sqr)?))
+        };
+        quotient(self.clone(), rhs.clone()).or_else(|_: FixError| {
+            let half = Complex::new(N::ONE.halve()?, N::ZERO);
+            quotient(
+                self.multiply(half.clone(), narrow)?,
+                rhs.multiply(half, narrow)?,
+            )
+        })
+    }
+}
+
+impl<N: MandelbrotNumber> Mul<N> for Complex<N> {
+    type Output = FixResult<Self>;
+
+    fn mul(self, rhs: N) -> Self::Output {
+        Ok(Complex::new(
+            self.r.widening_mul(rhs.clone()).truncate()?,
+            self.i.widening_mul(rhs).truncate()?,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::convert::{TryFrom, TryInto};
 
     use crate::complex::FixResult;
+    use crate::double_double::DoubleDouble;
     use crate::fix::fix2x61::Fix2x61;
     use crate::fix::fix4x123::Fix4x123;
 
@@ -111,7 +552,7 @@ mod tests {
     #[test]
     fn add_fix_64() -> FixResult<()> {
         let one = Fix2x61::one();
-        let two = one + one;
+        let two = one.try_add(one);
         assert_eq!(two?, Fix2x61::try_from(2)?);
         Ok(())
     }
@@ -125,15 +566,15 @@ mod tests {
     #[test]
     fn mult_fix_point() {
         let one = Fix2x61::one();
-        let long_one = one * one;
+        let long_one = one.widening_mul(one);
         assert_eq!(long_one, Fix4x123::one());
     }
 
     #[test]
     fn four() -> FixResult<()> {
         let one = Fix2x61::one();
-        let two = (one + one)?;
-        assert_eq!(two * two, Fix4x123::four());
+        let two = one.try_add(one)?;
+        assert_eq!(two.widening_mul(two), Fix4x123::four());
         Ok(())
     }
 
@@ -157,7 +598,7 @@ mod tests {
     fn power_of_two_minus_one() -> FixResult<()> {
         let two = Fix2x61::two();
         let two_minus_one = Fix2x61::power_of_two(-1)?;
-        assert_eq!(two * two_minus_one, Fix4x123::one());
+        assert_eq!(two.widening_mul(two_minus_one), Fix4x123::one());
         Ok(())
     }
 
@@ -171,10 +612,10 @@ mod tests {
     #[test]
     fn extend_two() -> FixResult<()> {
         let one = Fix2x61::one();
-        let two = (one + one)?;
+        let two = one.try_add(one)?;
         let extended: Fix4x123 = From::from(two);
         let long_one = Fix4x123::one();
-        let long_two = (long_one + long_one)?;
+        let long_two = long_one.try_add(long_one)?;
         assert_eq!(extended, long_two);
         Ok(())
     }
@@ -193,7 +634,10 @@ mod tests {
         let mut i = Complex::new(Fix2x61::zero(), Fix2x61::one());
         let j = i;
         i.iterate_mandelbrot(&j)?;
-        assert_eq!(i, Complex::new(Fix2x61(-1 << 61), Fix2x61::one()));
+        assert_eq!(
+            i,
+            Complex::new(Fix2x61::from_bits(-1 << 61), Fix2x61::one())
+        );
         Ok(())
     }
 
@@ -207,30 +651,92 @@ mod tests {
     #[test]
     fn float_into_minus_1() -> FixResult<()> {
         let result: Fix2x61 = (-1.0).try_into()?;
-        let minus_one = Fix2x61(-(Fix2x61::one().0));
+        let minus_one = Fix2x61::from_bits(-(Fix2x61::one().0));
         assert_eq!(result, minus_one);
         Ok(())
     }
 
-    // #[test]
-    // fn sq_i() {
-    //     let i: Complex = Complex::i();
-    //     let minus_one: Complex = -Complex::one();
-    //     assert_eq!(i * i, minus_one);
-    // }
-    //
-    // #[test]
-    // fn one_plus_one() -> FixResult<()> {
-    //     let one: Complex = Complex::from_parts(1, 0)?;
-    //     let two: Complex = Complex::from_parts(2, 0)?;
-    //     assert_eq!(one + one, two);
-    //     Ok(())
-    // }
-    //
-    // #[test]
-    // fn three_i() {
-    //     let three: Complex = Complex::from_parts(3, 0);
-    //     let i: Complex = Complex::i();
-    //     assert_eq!(three * i, Complex::from_parts(0, 3))
-    // }
+    #[test]
+    fn sq_i() -> FixResult<()> {
+        let i: Complex = Complex::i();
+        let minus_one: Complex = (-Complex::one())?;
+        assert_eq!((i * i)?, minus_one);
+        assert_eq!(i.square()?, minus_one);
+        Ok(())
+    }
+
+    #[test]
+    fn one_plus_one() -> FixResult<()> {
+        let one: Complex = Complex::one();
+        let two: Complex = Complex::new(Fix2x61::try_from(2)?, Fix2x61::zero());
+        assert_eq!((one + one)?, two);
+        assert_eq!((two - one)?, one);
+        Ok(())
+    }
+
+    #[test]
+    fn three_i() -> FixResult<()> {
+        let three = Fix2x61::try_from(3)?;
+        let i: Complex = Complex::i();
+        assert_eq!((i * three)?, Complex::new(Fix2x61::zero(), three));
+        Ok(())
+    }
+
+    #[test]
+    fn products_match_f64() -> FixResult<()> {
+        let a = Complex::new(Fix2x61::try_from(0.75)?, Fix2x61::try_from(-1.25)?);
+        let b = Complex::new(Fix2x61::try_from(-0.5)?, Fix2x61::try_from(1.5)?);
+        let product = (a * b)?;
+        assert_eq!(product.r.to_f64(), 0.75 * -0.5 - -1.25 * 1.5);
+        assert_eq!(product.i.to_f64(), 0.75 * 1.5 + -1.25 * -0.5);
+        assert_eq!((a * a.conj()?)?.i, Fix2x61::zero());
+        assert_eq!(a.norm_sqr()?.truncate()?, (a * a.conj()?)?.r);
+        assert_eq!(a.square()?, (a * a)?);
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_is_an_error() -> FixResult<()> {
+        let big = Complex::new(Fix2x61::try_from(2.5)?, Fix2x61::try_from(2.5)?);
+        assert!((big + big).is_err());
+        assert!((big * big).is_err());
+        assert!(big.norm_sqr().is_ok());
+        // -4 is in range but 4 isn't.
+        let min = Complex::new(Fix2x61::zero(), Fix2x61::from_bits(i64::MIN));
+        assert!((-min).is_err());
+        assert!(min.conj().is_err());
+        assert_eq!((-(-big)?)?, big);
+        Ok(())
+    }
+
+    #[test]
+    fn powers_match_repeated_products() -> FixResult<()> {
+        let z = Complex::new(Fix2x61::try_from(0.75)?, Fix2x61::try_from(-0.5)?);
+        let mut product: Complex = Complex::one();
+        for exponent in 0..8 {
+            assert_eq!(z.pow(exponent)?, product, "{}", exponent);
+            product = (product * z)?;
+        }
+        assert_eq!(Complex::<Fix2x61>::i().pow(6)?, (-Complex::one())?);
+        let big = Fix2x61::try_from(1.5)?;
+        assert!(Complex::new(big, big).pow(3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn quotients_undo_products() -> FixResult<()> {
+        let a = Complex::new(Fix2x61::try_from(0.75)?, Fix2x61::try_from(-1.25)?);
+        let b = Complex::new(Fix2x61::try_from(-0.5)?, Fix2x61::try_from(1.5)?);
+        let q = (((a * b)? / b)? - a)?;
+        assert!(q.r.abs() <= Fix2x61::from_bits(2) && q.i.abs() <= Fix2x61::from_bits(2));
+        // (1 + 2i) / (3 - i) = (1 + 7i) / 10
+        let (a, b) = (Complex::new(1.0, 2.0), Complex::new(3.0, -1.0));
+        assert_eq!((a / b)?, Complex::new(0.1, 0.7));
+        assert!((a / Complex::zero()).is_err());
+        let a = Complex::new(DoubleDouble::new(1.0), DoubleDouble::new(2.0));
+        let b = Complex::new(DoubleDouble::new(3.0), DoubleDouble::new(-1.0));
+        let error = ((((a / b)? * b)? - a)?).norm_sqr()?;
+        assert!(error.to_f64() < 1e-60);
+        Ok(())
+    }
 }
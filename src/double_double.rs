@@ -0,0 +1,286 @@
+use std::convert::TryFrom;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::complex::FixError;
+use crate::fix::fix2x61::Fix2x61;
+use crate::fix::fix4x123::Fix4x123;
+use crate::fix::fixn::FixN;
+
+// An unevaluated sum of two f64s, giving about 106 bits of mantissa.  `lo` is never more than half
+// an ulp of `hi`, so `hi` on its own is always the nearest f64 to the full value.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+impl DoubleDouble {
+    pub const ZERO: Self = DoubleDouble::new(0.0);
+    pub const TWO: Self = DoubleDouble::new(2.0);
+
+    pub const fn new(value: f64) -> Self {
+        DoubleDouble { hi: value, lo: 0.0 }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.hi
+    }
+
+    // Multiplying by a power of two is exact, as long as nothing underflows.
+    pub fn scale(&self, pow: i32) -> Self {
+        let factor = 2f64.powi(pow);
+        DoubleDouble {
+            hi: self.hi * factor,
+            lo: self.lo * factor,
+        }
+    }
+
+    pub fn exceeds(&self, limit: f64) -> bool {
+        let magnitude = self.hi.abs();
+        magnitude.is_nan()
+            || magnitude > limit
+            || (magnitude == limit && (self.lo == 0.0 || self.lo.signum() == self.hi.signum()))
+    }
+
+    // floor(self * 2^bits), which is how the fixed point types round.
+    fn to_scaled_i128(self, bits: i32) -> i128 {
+        let (hi, lo) = (self.scale(bits).hi, self.scale(bits).lo);
+        let (hi_floor, lo_floor) = (hi.floor(), lo.floor());
+        let fraction = (hi - hi_floor) + (lo - lo_floor);
+        hi_floor as i128 + lo_floor as i128 + fraction.floor() as i128
+    }
+
+    fn from_scaled_i128(value: i128, bits: i32) -> Self {
+        let hi = value as f64;
+        let lo = value.wrapping_sub(hi as i128) as f64;
+        quick_two_sum(hi, lo).scale(-bits)
+    }
+}
+
+fn two_sum(a: f64, b: f64) -> DoubleDouble {
+    let hi = a + b;
+    let b_virtual = hi - a;
+    let lo = (a - (hi - b_virtual)) + (b - b_virtual);
+    DoubleDouble { hi, lo }
+}
+
+// Only valid when |a| >= |b|.
+fn quick_two_sum(a: f64, b: f64) -> DoubleDouble {
+    let hi = a + b;
+    let lo = b - (hi - a);
+    DoubleDouble { hi, lo }
+}
+
+fn two_prod(a: f64, b: f64) -> DoubleDouble {
+    let hi = a * b;
+    let lo = a.mul_add(b, -hi);
+    DoubleDouble { hi, lo }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let s = two_sum(self.hi, rhs.hi);
+        let t = two_sum(self.lo, rhs.lo);
+        let s = quick_two_sum(s.hi, s.lo + t.hi);
+        quick_two_sum(s.hi, s.lo + t.lo)
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let p = two_prod(self.hi, rhs.hi);
+        quick_two_sum(p.hi, p.lo + (self.hi * rhs.lo + self.lo * rhs.hi))
+    }
+}
+
+// Long division, one f64's worth of quotient at a time.
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        let q1 = self.hi / rhs.hi;
+        let r = self - rhs * DoubleDouble::new(q1);
+        let q2 = r.hi / rhs.hi;
+        let r = r - rhs * DoubleDouble::new(q2);
+        let q3 = r.hi / rhs.hi;
+        quick_two_sum(q1, q2) + DoubleDouble::new(q3)
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(value: f64) -> Self {
+        DoubleDouble::new(value)
+    }
+}
+
+// Exact: Fix2x61 only has 64 significant bits.
+impl From<Fix2x61> for DoubleDouble {
+    fn from(value: Fix2x61) -> Self {
+        DoubleDouble::from_scaled_i128(value.0 as i128, 61)
+    }
+}
+
+// Rounds to the nearest 106 bits.
+impl From<Fix4x123> for DoubleDouble {
+    fn from(value: Fix4x123) -> Self {
+        DoubleDouble::from_scaled_i128(value.0, 123)
+    }
+}
+
+impl TryFrom<DoubleDouble> for Fix2x61 {
+    type Error = FixError;
+
+    fn try_from(value: DoubleDouble) -> Result<Self, Self::Error> {
+        if value.exceeds(4.0) {
+            Err(FixError::Overflow {
+                op: "Fix2x61::try_from(DoubleDouble)",
+            })
+        } else {
+            Ok(Fix2x61::from_bits(value.to_scaled_i128(61) as i64))
+        }
+    }
+}
+
+impl TryFrom<DoubleDouble> for Fix4x123 {
+    type Error = FixError;
+
+    fn try_from(value: DoubleDouble) -> Result<Self, Self::Error> {
+        if value.exceeds(16.0) {
+            Err(FixError::Overflow {
+                op: "Fix4x123::try_from(DoubleDouble)",
+            })
+        } else {
+            Ok(Fix4x123::from_bits(value.to_scaled_i128(123)))
+        }
+    }
+}
+
+impl<const LIMBS: usize> TryFrom<DoubleDouble> for FixN<LIMBS> {
+    type Error = FixError;
+
+    fn try_from(value: DoubleDouble) -> Result<Self, Self::Error> {
+        if value.exceeds(4.0) {
+            return Err(FixError::Overflow {
+                op: "FixN::try_from(DoubleDouble)",
+            });
+        }
+        FixN::try_from(value.hi)? + FixN::try_from(value.lo)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::{TryFrom, TryInto};
+
+    use anyhow::Error;
+
+    use crate::complex::{Complex, FixResult};
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::fix::fix4x123::Fix4x123;
+    use crate::fix::fixn::FixN;
+
+    use super::DoubleDouble;
+
+    #[test]
+    fn keeps_bits_f64_loses() {
+        let tiny = DoubleDouble::new(2f64.powi(-80));
+        let sum = DoubleDouble::new(1.0) + tiny;
+        assert_eq!(sum.hi, 1.0);
+        assert_eq!(sum - DoubleDouble::new(1.0), tiny);
+    }
+
+    #[test]
+    fn product_is_exact_to_106_bits() {
+        let a = DoubleDouble::new(1.0 + 2f64.powi(-52));
+        let square = a * a;
+        assert_eq!(square.hi, 1.0 + 2f64.powi(-51));
+        assert_eq!(square.lo, 2f64.powi(-104));
+    }
+
+    #[test]
+    fn fix2x61_round_trip() -> FixResult<()> {
+        for &bits in [
+            0,
+            1,
+            -1,
+            0x1234_5678_9abc_def1,
+            -0x7fff_ffff_ffff_ffff,
+            1 << 61,
+        ]
+        .iter()
+        {
+            let fix = Fix2x61::from_bits(bits);
+            let dd: DoubleDouble = fix.into();
+            assert_eq!(Fix2x61::try_from(dd)?, fix);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fix4x123_conversions() -> FixResult<()> {
+        let a: Fix2x61 = (-1.2345678).try_into()?;
+        let b: Fix2x61 = 0.87654321.try_into()?;
+        let product = a.widening_mul(b);
+        let dd = DoubleDouble::from(a) * DoubleDouble::from(b);
+        // The product has more than 106 significant bits, so only the top ones survive.
+        assert!((DoubleDouble::from(product) - dd).hi.abs() < 2f64.powi(-100));
+        let back = Fix4x123::try_from(dd)?;
+        assert!((back.0 - product.0).abs() < 1 << 24);
+        Ok(())
+    }
+
+    #[test]
+    fn fixn_conversion() -> FixResult<()> {
+        let dd = DoubleDouble::new(0.5) + DoubleDouble::new(-2f64.powi(-90));
+        let fixn: FixN<2> = dd.try_into()?;
+        let expected = (FixN::<2>::try_from(0.5)? - FixN::power_of_two(-90)?)?;
+        assert_eq!(fixn, expected);
+        Ok(())
+    }
+
+    // Squaring a number with k fractional bits gives one with 2k, so starting from a c with six
+    // fractional bits every value up to z5 fits exactly into both Fix4x123 and DoubleDouble.
+    #[test]
+    fn iteration_agrees_with_exact_fix4x123() -> Result<(), Error> {
+        for &(r, i) in [(-0.765625, 0.109375), (0.25, 0.515625), (-1.5, 0.015625)].iter() {
+            let c = Complex::new(Fix2x61::try_from(r)?, Fix2x61::try_from(i)?);
+            let dd_c = Complex::new(DoubleDouble::from(c.r), DoubleDouble::from(c.i));
+            let mut z = c;
+            let mut dd_z = dd_c;
+            for n in 0..4 {
+                let exact_r = (z.r.widening_mul(z.r) - z.i.widening_mul(z.i)) + From::from(c.r);
+                let exact_i = (z.r.widening_mul(z.i) + z.r.widening_mul(z.i)) + From::from(c.i);
+
+                dd_z.iterate_mandelbrot(&dd_c)?;
+
+                assert_eq!(Fix4x123::try_from(dd_z.r)?, exact_r, "{}, {}: {}", r, i, n);
+                assert_eq!(Fix4x123::try_from(dd_z.i)?, exact_i, "{}, {}: {}", r, i, n);
+                z = Complex::new(exact_r.truncate()?, exact_i.truncate()?);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,685 @@
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::fmt::{Debug, Formatter};
+use std::slice::Iter;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Error};
+
+use crate::complex::{overflow_escapes, Complex, FixError, FixResult};
+use crate::formula::{Formula, Iteration};
+use crate::number::{MandelbrotNumber, WideNumber};
+
+// Iteration formulas written out as expressions in z and c, like "z^3 + c*z + c".  Parsing gives
+// a tree, which compiling simplifies and then hands over to one of the built in formulas if it
+// matches one of their shapes.  Anything else is evaluated from the tree on every iteration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Z,
+    C,
+    // Constants are held as f64s until we know what type we're evaluating in.
+    Constant(Complex<f64>),
+    Neg(Box<Expression>),
+    Conj(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Sub(Box<Expression>, Box<Expression>),
+    Mul(Box<Expression>, Box<Expression>),
+    Div(Box<Expression>, Box<Expression>),
+    Pow(Box<Expression>, u8),
+}
+
+// Constants are always finite, so comparisons are too.
+impl Eq for Expression {}
+
+use Expression::*;
+
+// More number types than one set ever moves through as it's promoted.
+const NUMBER_TYPES: usize = 8;
+
+type Converted = (TypeId, Box<dyn Any + Send + Sync>);
+
+// A simplified expression, ready to iterate.  Its constants are converted to each number type
+// the first time it's evaluated in that type, rather than on every evaluation.
+pub struct Compiled {
+    expression: Expression,
+    // In the order evaluating comes across them.
+    constants: Vec<Complex<f64>>,
+    converted: [OnceLock<Converted>; NUMBER_TYPES],
+}
+
+impl Compiled {
+    fn new(expression: Expression) -> Compiled {
+        let mut constants = vec![];
+        expression.collect_constants(&mut constants);
+        Compiled {
+            expression,
+            constants,
+            converted: Default::default(),
+        }
+    }
+
+    fn convert<N: MandelbrotNumber>(&self) -> FixResult<Vec<Complex<N>>> {
+        self.constants
+            .iter()
+            .map(|k| Ok(Complex::new(N::from_f64(k.r)?, N::from_f64(k.i)?)))
+            .collect()
+    }
+
+    // Each number type takes the first free slot.  If they ever run out we convert every time.
+    fn constants<N: MandelbrotNumber>(&self) -> FixResult<Cow<'_, [Complex<N>]>> {
+        let id = TypeId::of::<N>();
+        for slot in self.converted.iter() {
+            let (found, constants) = slot.get_or_init(|| (id, Box::new(self.convert::<N>())));
+            if *found == id {
+                let constants = constants.downcast_ref::<FixResult<Vec<Complex<N>>>>();
+                return match constants.unwrap() {
+                    Ok(constants) => Ok(Cow::Borrowed(constants)),
+                    Err(e) => Err(e.clone()),
+                };
+            }
+        }
+        self.convert().map(Cow::Owned)
+    }
+
+    pub fn evaluate<N: MandelbrotNumber>(
+        &self,
+        z: &Complex<N>,
+        c: &Complex<N>,
+    ) -> FixResult<Complex<N>> {
+        let constants = self.constants()?;
+        self.expression.evaluate(z, c, &mut constants.iter())
+    }
+}
+
+impl Debug for Compiled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.expression.fmt(f)
+    }
+}
+
+impl PartialEq for Compiled {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression == other.expression
+    }
+}
+
+impl Eq for Compiled {}
+
+impl Expression {
+    // Simplifies, checks that every constant fits the range we iterate in, and picks the fastest
+    // way to iterate.  Formulas are shared by every point and last as long as the program, so
+    // anything that's not built in is leaked to keep them small.
+    pub fn compile(self) -> Result<Formula, Error> {
+        let folded = self.fold();
+        folded.check_constants()?;
+        Ok(match folded.shape() {
+            Some(formula) => formula,
+            None => Formula::Expression(Box::leak(Box::new(Compiled::new(folded)))),
+        })
+    }
+
+    fn shape(&self) -> Option<Formula> {
+        let (power, rest) = match self {
+            Add(a, b) => match (a.as_ref(), b.as_ref()) {
+                (Pow(base, d), rest) | (rest, Pow(base, d)) => ((base.as_ref(), *d), rest),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        match (power, rest) {
+            ((Z, d), C) if d >= 2 => Formula::multibrot(d as i32).ok(),
+            ((Conj(z), 2), C) if **z == Z => Some(Formula::Tricorn),
+            _ => None,
+        }
+    }
+
+    fn check_constants(&self) -> Result<(), Error> {
+        match self {
+            Z | C => Ok(()),
+            Constant(k) => {
+                if !(k.r.abs() < 4.0 && k.i.abs() < 4.0) {
+                    bail!(
+                        "constant {} is out of range: its parts must be less than 4 in magnitude",
+                        k
+                    )
+                }
+                Ok(())
+            }
+            Neg(a) | Conj(a) | Pow(a, _) => a.check_constants(),
+            Add(a, b) | Sub(a, b) | Mul(a, b) | Div(a, b) => {
+                a.check_constants()?;
+                b.check_constants()
+            }
+        }
+    }
+
+    // Works out everything that doesn't depend on z or c, and drops operations that don't do
+    // anything.  Constants are combined in f64, so they may be slightly out in the last bits.
+    pub fn fold(self) -> Expression {
+        let zero = Complex::new(0.0, 0.0);
+        let one = Complex::new(1.0, 0.0);
+        match self {
+            Z | C | Constant(_) => self,
+            Neg(a) => match a.fold() {
+                Constant(k) => Constant(Complex::new(-k.r, -k.i)),
+                Neg(a) => *a,
+                a => Neg(Box::new(a)),
+            },
+            Conj(a) => match a.fold() {
+                Constant(k) => Constant(Complex::new(k.r, -k.i)),
+                Conj(a) => *a,
+                a => Conj(Box::new(a)),
+            },
+            // Sums are taken apart into their terms, so that constants can be combined however
+            // they're bracketed.  Whatever's left comes back in the same order, with the constant
+            // at the end.
+            Add(..) | Sub(..) => {
+                let (mut terms, mut constant) = (vec![], zero);
+                self.terms(true, &mut terms, &mut constant);
+                let sum = terms.into_iter().fold(None, |sum, (positive, x)| {
+                    Some(match (sum, positive) {
+                        (None, true) => x,
+                        (None, false) => Neg(Box::new(x)).fold(),
+                        (Some(sum), true) => Add(Box::new(sum), Box::new(x)),
+                        (Some(sum), false) => Sub(Box::new(sum), Box::new(x)),
+                    })
+                });
+                match sum {
+                    Some(sum) if constant == zero => sum,
+                    Some(sum) => Add(Box::new(sum), Box::new(Constant(constant))),
+                    None => Constant(constant),
+                }
+            }
+            Mul(a, b) => match (a.fold(), b.fold()) {
+                (Constant(a), Constant(b)) => Constant(a.times(b)),
+                (Constant(k), _) | (_, Constant(k)) if k == zero => Constant(zero),
+                (Constant(k), x) | (x, Constant(k)) if k == one => x,
+                (a, b) if a == b => Pow(Box::new(a), 2).fold(),
+                (a, b) => Mul(Box::new(a), Box::new(b)),
+            },
+            Div(a, b) => match (a.fold(), b.fold()) {
+                (Constant(a), Constant(b)) if b != zero => Constant(a.divided_by(b)),
+                (x, Constant(k)) if k == one => x,
+                (a, b) => Div(Box::new(a), Box::new(b)),
+            },
+            Pow(a, d) => match a.fold() {
+                Constant(k) => Constant((1..d).fold(k, |p, _| p.times(k))),
+                x if d == 1 => x,
+                Pow(x, e) if (d as u32) * (e as u32) <= u8::MAX as u32 => Pow(x, d * e),
+                x => Pow(Box::new(x), d),
+            },
+        }
+    }
+
+    // Folds each term of a sum, adding up the ones that are constant.
+    fn terms(
+        self,
+        positive: bool,
+        terms: &mut Vec<(bool, Expression)>,
+        constant: &mut Complex<f64>,
+    ) {
+        match self {
+            Add(a, b) => {
+                a.terms(positive, terms, constant);
+                b.terms(positive, terms, constant);
+            }
+            Sub(a, b) => {
+                a.terms(positive, terms, constant);
+                b.terms(!positive, terms, constant);
+            }
+            x => match x.fold() {
+                sum @ (Add(..) | Sub(..)) => sum.terms(positive, terms, constant),
+                Constant(k) if positive => *constant = constant.plus(k),
+                Constant(k) => *constant = constant.minus(k),
+                x => terms.push((positive, x)),
+            },
+        }
+    }
+
+    fn collect_constants(&self, constants: &mut Vec<Complex<f64>>) {
+        match self {
+            Z | C => {}
+            Constant(k) => constants.push(*k),
+            Neg(a) | Conj(a) | Pow(a, _) => a.collect_constants(constants),
+            Add(a, b) | Sub(a, b) | Mul(a, b) | Div(a, b) => {
+                a.collect_constants(constants);
+                b.collect_constants(constants);
+            }
+        }
+    }
+
+    // Takes constants from the front of the list as it comes across them.
+    fn evaluate<N: MandelbrotNumber>(
+        &self,
+        z: &Complex<N>,
+        c: &Complex<N>,
+        k: &mut Iter<Complex<N>>,
+    ) -> FixResult<Complex<N>> {
+        Ok(match self {
+            Z => z.clone(),
+            C => c.clone(),
+            Constant(_) => k.next().unwrap().clone(),
+            Neg(a) => (-a.evaluate(z, c, k)?)?,
+            Conj(a) => a.evaluate(z, c, k)?.conj()?,
+            Add(a, b) => (a.evaluate(z, c, k)? + b.evaluate(z, c, k)?)?,
+            Sub(a, b) => (a.evaluate(z, c, k)? - b.evaluate(z, c, k)?)?,
+            Mul(a, b) => (a.evaluate(z, c, k)? * b.evaluate(z, c, k)?)?,
+            Div(a, b) => (a.evaluate(z, c, k)? / b.evaluate(z, c, k)?)?,
+            Pow(a, 2) => a.evaluate(z, c, k)?.square()?,
+            Pow(a, d) => a.evaluate(z, c, k)?.pow(*d as u32)?,
+        })
+    }
+}
+
+impl Iteration for Compiled {
+    fn iterate<N: MandelbrotNumber>(
+        &self,
+        z: &mut Complex<N>,
+        _state: &mut Complex<N>,
+        c: &Complex<N>,
+    ) -> FixResult<()> {
+        let next = self.evaluate(z, c).map_err(overflow_escapes)?;
+        if next.norm_sqr().map_err(overflow_escapes)?.exceeds_four() {
+            Err(FixError::Escaped)
+        } else {
+            *z = next;
+            Ok(())
+        }
+    }
+}
+
+// A recursive descent parser over this grammar, where positions in errors count characters from
+// zero:
+//
+//   sum     = product (("+" | "-") product)*
+//   product = unary (("*" | "/") unary)*
+//   unary   = "-" unary | power
+//   power   = value ("^" whole number)?
+//   value   = number | "z" | "c" | "i" | "conj" "(" sum ")" | "(" sum ")"
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    length: usize,
+}
+
+fn tokenise(s: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let ch = chars[i];
+        if ch.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = if ch.is_ascii_digit() || ch == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // An exponent, as long as there's a number for it.
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let sign = (i + 1 < chars.len() && "+-".contains(chars[i + 1])) as usize;
+                if i + 1 + sign < chars.len() && chars[i + 1 + sign].is_ascii_digit() {
+                    i += 1 + sign;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            match f64::from_str(&text) {
+                Ok(v) => Token::Number(v),
+                Err(_) => bail!("'{}' at character {} is not a valid number", text, start),
+            }
+        } else if ch.is_alphabetic() {
+            while i < chars.len() && chars[i].is_alphanumeric() {
+                i += 1;
+            }
+            Token::Name(chars[start..i].iter().collect())
+        } else if "+-*/^()".contains(ch) {
+            i += 1;
+            Token::Symbol(ch)
+        } else {
+            bail!(
+                "character {} is '{}' but should be part of a number or a name, or one of + - * / \
+                 ^ ( )",
+                i,
+                ch
+            )
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map(|&(p, _)| p)
+            .unwrap_or(self.length)
+    }
+
+    fn accept(&mut self, symbol: char) -> bool {
+        let found = self.peek() == Some(&Token::Symbol(symbol));
+        self.next += found as usize;
+        found
+    }
+
+    fn describe(token: &Token) -> String {
+        match token {
+            Token::Number(v) => v.to_string(),
+            Token::Name(name) => name.clone(),
+            Token::Symbol(s) => s.to_string(),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expression, Error> {
+        let mut e = self.product()?;
+        loop {
+            if self.accept('+') {
+                e = Add(Box::new(e), Box::new(self.product()?));
+            } else if self.accept('-') {
+                e = Sub(Box::new(e), Box::new(self.product()?));
+            } else {
+                return Ok(e);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Expression, Error> {
+        let mut e = self.unary()?;
+        loop {
+            if self.accept('*') {
+                e = Mul(Box::new(e), Box::new(self.unary()?));
+            } else if self.accept('/') {
+                e = Div(Box::new(e), Box::new(self.unary()?));
+            } else {
+                return Ok(e);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, Error> {
+        if self.accept('-') {
+            Ok(Neg(Box::new(self.unary()?)))
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Expression, Error> {
+        let base = self.value()?;
+        if !self.accept('^') {
+            return Ok(base);
+        }
+        let position = self.position();
+        match self.tokens.get(self.next) {
+            Some((_, Token::Number(d))) if d.fract() == 0.0 && (1.0..=255.0).contains(d) => {
+                self.next += 1;
+                Ok(Pow(Box::new(base), *d as u8))
+            }
+            Some((_, token)) => bail!(
+                "exponent at character {} is {} but should be a whole number from 1 to {}",
+                position,
+                Parser::describe(token),
+                u8::MAX
+            ),
+            None => bail!(
+                "formula ends at character {} but should continue with an exponent",
+                position
+            ),
+        }
+    }
+
+    fn value(&mut self) -> Result<Expression, Error> {
+        let position = self.position();
+        let token = match self.tokens.get(self.next) {
+            Some((_, token)) => token.clone(),
+            None => bail!(
+                "formula ends at character {} but should continue with a value",
+                position
+            ),
+        };
+        self.next += 1;
+        Ok(match token {
+            Token::Number(v) => Constant(Complex::new(v, 0.0)),
+            Token::Name(name) => match name.as_str() {
+                "z" => Z,
+                "c" => C,
+                "i" => Constant(Complex::new(0.0, 1.0)),
+                "conj" => {
+                    if !self.accept('(') {
+                        bail!("'conj' at character {} should be followed by '('", position)
+                    }
+                    Conj(Box::new(self.bracketed(self.position() - 1)?))
+                }
+                _ => bail!(
+                    "'{}' at character {} is not z, c, i or conj",
+                    name,
+                    position
+                ),
+            },
+            Token::Symbol('(') => self.bracketed(position)?,
+            token => bail!(
+                "character {} is '{}' but should start a value: a number, z, c, i, conj or '('",
+                position,
+                Parser::describe(&token)
+            ),
+        })
+    }
+
+    // The rest of a bracket opened at `open`.
+    fn bracketed(&mut self, open: usize) -> Result<Expression, Error> {
+        let e = self.sum()?;
+        if !self.accept(')') {
+            bail!("'(' at character {} is never closed", open)
+        }
+        Ok(e)
+    }
+}
+
+impl FromStr for Expression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenise(s)?,
+            next: 0,
+            length: s.chars().count(),
+        };
+        let e = parser.sum()?;
+        if let Some(token) = parser.peek() {
+            bail!(
+                "character {} is '{}' but should be an operator or the end of the formula",
+                parser.position(),
+                Parser::describe(token)
+            )
+        }
+        Ok(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::formula::Formula;
+    use crate::point::Point;
+
+    use super::Expression;
+
+    fn compile(s: &str) -> Result<Formula, Error> {
+        Expression::from_str(s)?.compile()
+    }
+
+    fn error(s: &str) -> String {
+        Expression::from_str(s).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn reports_where_parsing_failed() {
+        assert_eq!(
+            error("z^2 + * c"),
+            "character 6 is '*' but should start a value: a number, z, c, i, conj or '('"
+        );
+        assert_eq!(
+            error("z^2 + w"),
+            "'w' at character 6 is not z, c, i or conj"
+        );
+        assert_eq!(error("(z^2 + c"), "'(' at character 0 is never closed");
+        assert_eq!(
+            error("z^2.5 + c"),
+            "exponent at character 2 is 2.5 but should be a whole number from 1 to 255"
+        );
+        assert_eq!(
+            error("z^2 + c)"),
+            "character 7 is ')' but should be an operator or the end of the formula"
+        );
+        assert_eq!(
+            error("z^2 +"),
+            "formula ends at character 5 but should continue with a value"
+        );
+        assert_eq!(
+            error("z % c"),
+            "character 2 is '%' but should be part of a number or a name, or one of + - * / ^ ( )"
+        );
+        assert_eq!(
+            error("z + 1.2.3"),
+            "'1.2.3' at character 4 is not a valid number"
+        );
+        assert!(compile("z^2 + 5").is_err());
+        assert!(compile("z^2 + 5 - 2").is_ok());
+    }
+
+    #[test]
+    fn folds_constants() -> Result<(), Error> {
+        use super::Expression::*;
+        let folded = Expression::from_str("z*z + (1 - 0.5) * i + 0*c")?.fold();
+        assert_eq!(
+            folded,
+            Add(
+                Box::new(Pow(Box::new(Z), 2)),
+                Box::new(Constant(Complex::new(0.0, 0.5)))
+            )
+        );
+        assert_eq!(
+            Expression::from_str("(z^2)^3 * 1")?.fold(),
+            Pow(Box::new(Z), 6)
+        );
+        assert_eq!(
+            Expression::from_str("--c / (2 - i)^1")?.fold(),
+            Div(Box::new(C), Box::new(Constant(Complex::new(2.0, -1.0))))
+        );
+        assert_eq!(
+            Expression::from_str("(1 + i) / (1 - i)")?.fold(),
+            Constant(Complex::new(0.0, 1.0))
+        );
+        assert_eq!(
+            Expression::from_str("1 + z^2 - (2 - c) + 0.5")?.fold(),
+            Add(
+                Box::new(Add(Box::new(Pow(Box::new(Z), 2)), Box::new(C))),
+                Box::new(Constant(Complex::new(-0.5, 0.0)))
+            )
+        );
+        assert_eq!(
+            Expression::from_str("1 - (z + 1)")?.fold(),
+            Neg(Box::new(Z))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn picks_built_in_formulas() -> Result<(), Error> {
+        assert_eq!(compile("z^2 + c")?, Formula::MANDELBROT);
+        assert_eq!(compile("c + z * z")?, Formula::MANDELBROT);
+        assert_eq!(compile("z^5 + c")?, Formula::multibrot(5)?);
+        assert_eq!(compile("conj(z)^2 + c")?, Formula::Tricorn);
+        assert!(matches!(compile("z^2 + 2*c")?, Formula::Expression(_)));
+        assert!(matches!(compile("z + c")?, Formula::Expression(_)));
+        Ok(())
+    }
+
+    fn orbit(formula: Formula, r: f64, i: f64, n: u64) -> Result<Point, Error> {
+        let c = Complex::new(Fix2x61::try_from(r)?, Fix2x61::try_from(i)?);
+        let mut p = Point::new(c);
+        p.formula = formula;
+        p.iterate_n(n)?;
+        Ok(p)
+    }
+
+    #[test]
+    fn evaluates_like_the_built_in_formulas() -> Result<(), Error> {
+        // Written so that nothing matches a fast path.
+        let slow_mandelbrot = compile("z*(z + 0*c) + c*1 - (0 - 0)")?;
+        assert_eq!(slow_mandelbrot, Formula::MANDELBROT);
+        let slow_mandelbrot = compile("z*z*1 + c/1 + (z - z)")?;
+        assert!(matches!(slow_mandelbrot, Formula::Expression(_)));
+        for &(r, i) in [(-0.75, 0.1), (0.25, 0.5), (-1.5, 0.0), (0.3, -0.6)].iter() {
+            let slow = orbit(slow_mandelbrot, r, i, 200)?;
+            let fast = orbit(Formula::MANDELBROT, r, i, 200)?;
+            assert_eq!(slow.value(), fast.value(), "{}, {}", r, i);
+            assert_eq!(
+                (slow.escaped, slow.iterations),
+                (fast.escaped, fast.iterations)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn evaluates_new_formulas() -> Result<(), Error> {
+        let formula = compile("z^3 + c*z + c")?;
+        let (z, c) = (Complex::new(0.5, -0.25), Complex::new(-0.25, 0.125));
+        let (z3, cz) = (z.pow(3)?, (c * z)?);
+        let step = match formula {
+            Formula::Expression(e) => e.evaluate(&z, &c)?,
+            _ => panic!("{:?}", formula),
+        };
+        assert_eq!(step, ((z3 + cz)? + c)?);
+        assert!(orbit(formula, 1.0, 1.0, 10)?.escaped);
+        assert!(!orbit(formula, -0.1, 0.1, 1000)?.escaped);
+        Ok(())
+    }
+
+    // Each type's constants are converted once and kept.
+    #[test]
+    fn constants_are_converted_once_per_type() -> Result<(), Error> {
+        let compiled = match compile("z^2 + (0.5 - i)*c - 0.25")? {
+            Formula::Expression(e) => e,
+            formula => panic!("{:?}", formula),
+        };
+        let step = |z: Complex<f64>, c| -> Result<Complex<f64>, Error> {
+            Ok(((z.square()? + (Complex::new(0.5, -1.0) * c)?)? - Complex::new(0.25, 0.0))?)
+        };
+        let (z, c) = (Complex::new(0.5, -0.25), Complex::new(-0.25, 0.125));
+        assert_eq!(compiled.evaluate(&z, &c)?, step(z, c)?);
+        assert_eq!(compiled.evaluate(&z, &c)?, step(z, c)?);
+        let (fz, fc) = (z.convert::<Fix2x61>()?, c.convert::<Fix2x61>()?);
+        assert_eq!(compiled.evaluate(&fz, &fc)?, step(z, c)?.convert()?);
+        let filled = compiled
+            .converted
+            .iter()
+            .filter(|slot| slot.get().is_some());
+        assert_eq!(filled.count(), 2);
+        Ok(())
+    }
+}
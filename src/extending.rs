@@ -1,8 +1,18 @@
-use num_traits::{Bounded, PrimInt};
-use std::ops::Not;
+use std::any::type_name;
+use std::convert::TryFrom;
+use std::fmt::{Binary, Debug, Display, Formatter, LowerHex};
+use std::str::FromStr;
 
-trait Bigger: PrimInt {
-    type Larger: PrimInt;
+use num_traits::{
+    AsPrimitive, CheckedNeg, CheckedRem, NumCast, PrimInt, Signed, WrappingAdd, WrappingNeg,
+    WrappingSub,
+};
+
+use crate::complex::{FixError, FixResult};
+use crate::fix::format::{parse_fixed, write_fixed};
+
+pub trait Bigger: PrimInt + 'static {
+    type Larger: PrimInt + AsPrimitive<Self>;
 }
 
 macro_rules! bigger {
@@ -22,29 +32,397 @@ bigger!(i16, i32);
 bigger!(i32, i64);
 bigger!(i64, i128);
 
-#[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
-struct Extending<T: Bigger>(T);
+// A signed integer that can hold a fixed point value.  The operations that need more bits than the
+// integer has are done in Bigger::Larger where there is one; i128 does them by hand.
+pub trait FixedStorage:
+    PrimInt
+    + Signed
+    + CheckedNeg
+    + CheckedRem
+    + WrappingAdd
+    + WrappingSub
+    + WrappingNeg
+    + Default
+    + Debug
+    + Send
+    + Sync
+    + 'static
+{
+    const BITS: u32;
+    const MIN: Self;
+    const MAX: Self;
+    const ZERO: Self;
+    const ONE: Self;
+
+    // floor(self * rhs / 2^shift), and whether that overflowed.
+    fn overflowing_mul_shift(self, rhs: Self, shift: u32) -> (Self, bool);
+    // floor(self * 2^shift / rhs).
+    fn checked_div_shift(self, rhs: Self, shift: u32) -> Option<Self>;
+    // floor(sqrt(self * 2^shift)).
+    fn checked_sqrt_shift(self, shift: u32) -> Option<Self>;
+}
+
+#[inline]
+fn widen<T: Bigger>(value: T) -> T::Larger {
+    <T::Larger as NumCast>::from(value).unwrap()
+}
+
+macro_rules! storage_via_larger {
+    ($t:ty) => {
+        impl FixedStorage for $t {
+            const BITS: u32 = <$t>::BITS;
+            const MIN: Self = <$t>::MIN;
+            const MAX: Self = <$t>::MAX;
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn overflowing_mul_shift(self, rhs: Self, shift: u32) -> (Self, bool) {
+                let product = (widen(self) * widen(rhs)) >> shift as usize;
+                match <$t as NumCast>::from(product) {
+                    Some(product) => (product, false),
+                    None => (product.as_(), true),
+                }
+            }
+
+            fn checked_div_shift(self, rhs: Self, shift: u32) -> Option<Self> {
+                if rhs == 0 {
+                    return None;
+                }
+                let (n, d) = (widen(self) << shift as usize, widen(rhs));
+                let (q, r) = (n / d, n % d);
+                let q = if r != 0 && (r < 0) != (d < 0) {
+                    q - 1
+                } else {
+                    q
+                };
+                <$t as NumCast>::from(q)
+            }
 
-impl<T: Bigger> From<T> for Extending<T> {
-    fn from(f: T) -> Self {
-        Extending(f)
+            fn checked_sqrt_shift(self, shift: u32) -> Option<Self> {
+                if self < 0 {
+                    None
+                } else {
+                    <$t as NumCast>::from(((self as u128) << shift).isqrt())
+                }
+            }
+        }
+    };
+}
+
+storage_via_larger!(i8);
+storage_via_larger!(i16);
+storage_via_larger!(i32);
+storage_via_larger!(i64);
+
+impl FixedStorage for i128 {
+    const BITS: u32 = i128::BITS;
+    const MIN: Self = i128::MIN;
+    const MAX: Self = i128::MAX;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+
+    fn overflowing_mul_shift(self, rhs: Self, shift: u32) -> (Self, bool) {
+        let negative = (self < 0) != (rhs < 0);
+        let (hi, lo) = widening_mul_u128(self.unsigned_abs(), rhs.unsigned_abs());
+        let (mut magnitude, remainder, mut overflow) = if shift == 0 {
+            (lo, 0, hi != 0)
+        } else {
+            let remainder = lo & ((1 << shift) - 1);
+            (
+                (hi << (128 - shift)) | (lo >> shift),
+                remainder,
+                hi >> shift != 0,
+            )
+        };
+        if negative && remainder != 0 {
+            magnitude = magnitude.wrapping_add(1);
+            overflow |= magnitude == 0;
+        }
+        (
+            signed(negative, magnitude),
+            overflow || !fits(negative, magnitude),
+        )
+    }
+
+    fn checked_div_shift(self, rhs: Self, shift: u32) -> Option<Self> {
+        if rhs == 0 {
+            return None;
+        }
+        let negative = (self < 0) != (rhs < 0);
+        let (n, d) = (self.unsigned_abs(), rhs.unsigned_abs());
+        // Long division of n * 2^shift by d, one bit at a time.  The remainder stays below d,
+        // which is at most 2^127, so doubling it can't overflow.
+        let (mut q, mut r) = (0u128, 0u128);
+        for bit in (0..128 + shift).rev() {
+            let next = if bit >= shift {
+                (n >> (bit - shift)) & 1
+            } else {
+                0
+            };
+            r = (r << 1) | next;
+            if q >> 127 != 0 {
+                return None;
+            }
+            q <<= 1;
+            if r >= d {
+                r -= d;
+                q |= 1;
+            }
+        }
+        if negative && r != 0 {
+            q = q.checked_add(1)?;
+        }
+        if fits(negative, q) {
+            Some(signed(negative, q))
+        } else {
+            None
+        }
+    }
+
+    fn checked_sqrt_shift(self, shift: u32) -> Option<Self> {
+        if self < 0 {
+            return None;
+        }
+        let n = self as u128;
+        let target = if shift == 0 {
+            (0, n)
+        } else {
+            (n >> (128 - shift), n << shift)
+        };
+        let mut root = 0u128;
+        for bit in (0..((127 + shift) / 2 + 1).min(127)).rev() {
+            let candidate = root | (1 << bit);
+            if widening_mul_u128(candidate, candidate) <= target {
+                root = candidate;
+            }
+        }
+        Some(root as i128)
     }
 }
 
-impl<T: Bigger> Bounded for Extending<T> {
-    fn min_value() -> Self {
-        T::min_value().into()
+// The full product of two u128s, as its high and low halves.
+pub(crate) fn widening_mul_u128(a: u128, b: u128) -> (u128, u128) {
+    let mask = (1u128 << 64) - 1;
+    let (a_hi, a_lo, b_hi, b_lo) = (a >> 64, a & mask, b >> 64, b & mask);
+    let (mid, mid_carry) = (a_lo * b_hi).overflowing_add(a_hi * b_lo);
+    let (lo, lo_carry) = (a_lo * b_lo).overflowing_add(mid << 64);
+    let hi = a_hi * b_hi + (mid >> 64) + ((mid_carry as u128) << 64) + lo_carry as u128;
+    (hi, lo)
+}
+
+fn fits(negative: bool, magnitude: u128) -> bool {
+    magnitude < 1 << 127 || (negative && magnitude == 1 << 127)
+}
+
+fn signed(negative: bool, magnitude: u128) -> i128 {
+    if negative {
+        (magnitude as i128).wrapping_neg()
+    } else {
+        magnitude as i128
+    }
+}
+
+// A fixed point number: one sign bit, FRAC fractional bits, and whatever's left of T for the
+// integer part.  Its arithmetic lives in fix/ops.rs.
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fixed<T, const FRAC: u32>(pub(crate) T);
+
+impl<T: FixedStorage, const FRAC: u32> Fixed<T, FRAC> {
+    pub const FRACTIONAL_BITS: u32 = FRAC;
+    pub const INTEGER_BITS: u32 = {
+        assert!(FRAC < T::BITS, "FRAC has to leave room for the sign bit");
+        T::BITS - 1 - FRAC
+    };
+    pub const ZERO: Self = Fixed(T::ZERO);
+
+    pub const fn from_bits(bits: T) -> Self {
+        // Evaluating INTEGER_BITS rejects a FRAC that's too big at compile time.
+        let _ = Self::INTEGER_BITS;
+        Fixed(bits)
+    }
+
+    pub fn to_bits(self) -> T {
+        self.0
     }
 
-    fn max_value() -> Self {
-        T::max_value().into()
+    // 2^pow, if it's representable.
+    pub fn try_power_of_two(pow: i32) -> FixResult<Self> {
+        if pow >= Self::INTEGER_BITS as i32 || pow < -(FRAC as i32) {
+            Err(FixError::Overflow { op: "power_of_two" })
+        } else {
+            Ok(Fixed(T::one() << (FRAC as i32 + pow) as usize))
+        }
+    }
+
+    pub fn halve(&self) -> FixResult<Self> {
+        let val = self.0 >> 1;
+        if val.is_zero() {
+            Err(FixError::Underflow { op: "Fixed::halve" })
+        } else {
+            Ok(Fixed(val))
+        }
+    }
+
+    // floor(log2(|self|)), or None for zero.
+    pub fn exponent(&self) -> Option<i32> {
+        match self.to_i128().unsigned_abs() {
+            0 => None,
+            magnitude => Some(127 - magnitude.leading_zeros() as i32 - FRAC as i32),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64().unwrap() / 2f64.powi(FRAC as i32)
+    }
+
+    fn to_i128(self) -> i128 {
+        self.0.to_i128().unwrap()
+    }
+}
+
+impl<T: FixedStorage + Bigger, const FRAC: u32> Fixed<T, FRAC>
+where
+    T::Larger: FixedStorage,
+{
+    // The same value with more bits.
+    #[inline]
+    pub fn widen<const WIDE: u32>(self) -> Fixed<T::Larger, WIDE> {
+        const { assert!(WIDE >= FRAC, "widen can't drop fractional bits") };
+        Fixed(widen(self.0) << (WIDE - FRAC) as usize)
+    }
+
+    // The exact product, in a type with twice the bits.
+    #[inline]
+    pub fn widening_mul<const WIDE: u32>(self, rhs: Self) -> Fixed<T::Larger, WIDE> {
+        const { assert!(WIDE >= 2 * FRAC, "widening_mul can't drop fractional bits") };
+        Fixed((widen(self.0) * widen(rhs.0)) << (WIDE - 2 * FRAC) as usize)
+    }
+}
+
+pub(crate) fn parse<T: FixedStorage, const FRAC: u32>(
+    s: &str,
+    radix: Option<u32>,
+) -> Result<Fixed<T, FRAC>, anyhow::Error> {
+    let bits = parse_fixed(s, radix, FRAC, Fixed::<T, FRAC>::INTEGER_BITS)?;
+    Ok(Fixed(<T as NumCast>::from(bits).unwrap()))
+}
+
+impl<T: FixedStorage, const FRAC: u32> Fixed<T, FRAC> {
+    fn write(&self, f: &mut Formatter<'_>, base: u32) -> std::fmt::Result {
+        let _ = Self::INTEGER_BITS;
+        let bits = self.to_i128();
+        write_fixed(f, bits < 0, bits.unsigned_abs(), FRAC, base)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Debug for Fixed<T, FRAC> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple(&format!("Fixed<{}, {}>", type_name::<T>(), FRAC))
+            .field(&format_args!("{}", self))
+            .finish()
+    }
+}
+
+// Exact: every value has a terminating decimal expansion, of at most FRAC fractional digits.
+impl<T: FixedStorage, const FRAC: u32> Display for Fixed<T, FRAC> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write(f, 10)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> LowerHex for Fixed<T, FRAC> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write(f, 16)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Binary for Fixed<T, FRAC> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write(f, 2)
+    }
+}
+
+// Decimal, or hex and binary with a 0x or 0b prefix, rounded to the nearest representable value.
+impl<T: FixedStorage, const FRAC: u32> FromStr for Fixed<T, FRAC> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s, None)
+    }
+}
+
+// Rounds towards zero.
+impl<T: FixedStorage, const FRAC: u32> TryFrom<f64> for Fixed<T, FRAC> {
+    type Error = FixError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        let limit = 2f64.powi(Self::INTEGER_BITS as i32);
+        match <T as NumCast>::from(value * 2f64.powi(FRAC as i32)) {
+            Some(bits) if value.abs() < limit => Ok(Fixed(bits)),
+            _ => Err(FixError::Overflow {
+                op: "Fixed::try_from(f64)",
+            }),
+        }
     }
 }
 
-impl<T: Bigger> Not for Extending<T> {
-    type Output = Extending<<T as std::ops::Not>::Output>;
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::fix::fix2x29::{Fix2x29, Fix4x59};
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::fix::fix4x123::Fix4x123;
+
+    use num_traits::One;
+
+    use super::Fixed;
+
+    const VALUES: [f64; 7] = [0.5, -0.75, 1.25, -1.9999, 0.0001, -0.3, 1.7];
+
+    #[test]
+    fn narrow_instances() -> Result<(), Error> {
+        for &a in VALUES.iter() {
+            for &b in VALUES.iter() {
+                let (na, nb) = (Fix2x29::try_from(a)?, Fix2x29::try_from(b)?);
+                let (a, b) = (na.to_f64(), nb.to_f64());
+                // Both values have at most 29 fractional bits, so these are all exact.
+                let product: Fix4x59 = na.widening_mul(nb);
+                assert_eq!(product.to_f64(), a * b);
+                let product: Fix4x123 = Fix2x61::try_from(a)?.widening_mul(Fix2x61::try_from(b)?);
+                assert_eq!(product.to_f64(), a * b);
+                let wide: Fix4x59 = na.widen();
+                assert_eq!(wide.to_f64(), a);
+
+                let (ta, tb) = (
+                    Fixed::<i16, 12>::try_from(a)?,
+                    Fixed::<i16, 12>::try_from(b)?,
+                );
+                if let Some(product) = ta.checked_mul(tb) {
+                    assert!((product.to_f64() - ta.to_f64() * tb.to_f64()).abs() < 1.0 / 4096.0);
+                }
+            }
+        }
+        assert_eq!(Fix2x29::MAX.to_string(), "3.99999999813735485076904296875");
+        assert_eq!(
+            Fixed::<i16, 12>::one().checked_shl(2),
+            Some(Fixed::<i16, 12>::try_from(4.0)?)
+        );
+        Ok(())
+    }
 
-    fn not(self) -> Self::Output {
-        self.0.not().into()
+    #[test]
+    fn prints_all_fractional_bits() {
+        type Fraction = Fixed<i128, 127>;
+        assert_eq!(Fraction::from_bits(1 << 126).to_string(), "0.5");
+        assert_eq!(Fraction::from_bits(i128::MIN).to_string(), "-1");
+        assert_eq!(Fraction::from_bits(1).to_string().len(), "0.".len() + 127);
+        assert_eq!(format!("{:x}", Fraction::from_bits(3 << 125)), "0.c");
+        assert_eq!(
+            format!("{:.3}", Fixed::<i128, 125>::from_bits(i128::MAX)),
+            "4.000"
+        );
     }
 }
@@ -0,0 +1,7 @@
+use crate::extending::Fixed;
+
+// Fix2x61's layout in half the bits, for when speed matters more than depth.
+pub type Fix2x29 = Fixed<i32, 29>;
+
+// Wide enough for the exact product of two Fix2x29s.
+pub type Fix4x59 = Fixed<i64, 59>;
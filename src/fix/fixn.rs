@@ -0,0 +1,705 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::{Debug, Formatter};
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::complex::{FixError, FixResult};
+use crate::fix::fix2x61::Fix2x61;
+
+// One sign bit, two int bits, 64 * LIMBS - 3 mantissa bits.  Limbs are stored least significant
+// first, and FixN<1> has exactly the same layout as Fix2x61.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct FixN<const LIMBS: usize>(pub(crate) [u64; LIMBS]);
+
+// The product of two FixN<LIMBS>: one sign bit, four int bits, 128 * LIMBS - 5 mantissa bits.
+// This is to FixN what Fix4x123 is to Fix2x61.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct FixNWide<const LIMBS: usize>(pub(crate) [[u64; LIMBS]; 2]);
+
+impl<const LIMBS: usize> FixN<LIMBS> {
+    pub const FRACTIONAL_BITS: usize = 64 * LIMBS - 3;
+
+    pub const fn zero() -> Self {
+        FixN([0; LIMBS])
+    }
+
+    pub const fn one() -> Self {
+        let mut limbs = [0; LIMBS];
+        limbs[LIMBS - 1] = 1 << 61;
+        FixN(limbs)
+    }
+
+    pub const fn two() -> Self {
+        let mut limbs = [0; LIMBS];
+        limbs[LIMBS - 1] = 1 << 62;
+        FixN(limbs)
+    }
+
+    // The smallest positive value.
+    pub const fn epsilon() -> Self {
+        let mut limbs = [0; LIMBS];
+        limbs[0] = 1;
+        FixN(limbs)
+    }
+
+    pub fn power_of_two(pow: i32) -> FixResult<Self> {
+        if pow > 1 || pow < -(Self::FRACTIONAL_BITS as i32) {
+            Err(FixError::Overflow {
+                op: "FixN::power_of_two",
+            })
+        } else {
+            let bit = (Self::FRACTIONAL_BITS as i32 + pow) as usize;
+            let mut limbs = [0; LIMBS];
+            limbs[bit / 64] = 1 << (bit % 64);
+            Ok(FixN(limbs))
+        }
+    }
+
+    pub fn halve(&self) -> FixResult<Self> {
+        let mut limbs = self.0;
+        shift_right(&mut limbs, 1);
+        if limbs.iter().all(|&l| l == 0) {
+            Err(FixError::Underflow { op: "FixN::halve" })
+        } else {
+            Ok(FixN(limbs))
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        is_negative(&self.0)
+    }
+
+    // floor(log2(|self|)), or None for zero.
+    pub fn exponent(&self) -> Option<i32> {
+        let mut magnitude = self.0;
+        if self.is_negative() {
+            negate(&mut magnitude);
+        }
+        let (i, top) = magnitude.iter().enumerate().rev().find(|(_, &l)| l != 0)?;
+        Some((64 * i + 63 - top.leading_zeros() as usize) as i32 - Self::FRACTIONAL_BITS as i32)
+    }
+
+    // Changes the number of limbs, dropping low bits (rounding towards negative infinity) if the
+    // new type is narrower.
+    pub fn resize<const OTHER: usize>(&self) -> FixN<OTHER> {
+        let mut limbs = [0; OTHER];
+        if OTHER >= LIMBS {
+            limbs[OTHER - LIMBS..].copy_from_slice(&self.0);
+        } else {
+            limbs.copy_from_slice(&self.0[LIMBS - OTHER..]);
+        }
+        FixN(limbs)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let mut magnitude = self.0;
+        if self.is_negative() {
+            negate(&mut magnitude);
+        }
+        let value = magnitude
+            .iter()
+            .enumerate()
+            .map(|(i, &l)| l as f64 * 2f64.powi(64 * i as i32 - Self::FRACTIONAL_BITS as i32))
+            .sum::<f64>();
+        if self.is_negative() {
+            -value
+        } else {
+            value
+        }
+    }
+}
+
+impl<const LIMBS: usize> FixNWide<LIMBS> {
+    pub const ZERO: Self = FixNWide([[0; LIMBS]; 2]);
+
+    pub const fn one() -> Self {
+        let mut limbs = [[0; LIMBS]; 2];
+        limbs[1][LIMBS - 1] = 1 << 59;
+        FixNWide(limbs)
+    }
+
+    pub const fn four() -> Self {
+        let mut limbs = [[0; LIMBS]; 2];
+        limbs[1][LIMBS - 1] = 1 << 61;
+        FixNWide(limbs)
+    }
+
+    // True if the magnitude of this value is at least four, which is both the escape condition
+    // and the limit of what can be truncated back to a FixN.
+    pub fn exceeds_four(&self) -> bool {
+        exceeds_four(self.0.as_flattened())
+    }
+
+    pub fn is_negative(&self) -> bool {
+        is_negative(self.0.as_flattened())
+    }
+
+    // floor(self / rhs), by long division one bit at a time.
+    pub fn checked_div(&self, rhs: &Self) -> FixResult<FixN<LIMBS>> {
+        let quotient =
+            divide(self.0.as_flattened(), rhs.0.as_flattened()).ok_or(FixError::Overflow {
+                op: "FixNWide::checked_div",
+            })?;
+        let mut limbs = [0; LIMBS];
+        limbs.copy_from_slice(&quotient);
+        Ok(FixN(limbs))
+    }
+
+    pub fn truncate(&self) -> FixResult<FixN<LIMBS>> {
+        if self.exceeds_four() {
+            Err(FixError::Overflow {
+                op: "FixN::truncate",
+            })
+        } else {
+            let mut wide = self.0;
+            shift_right(wide.as_flattened_mut(), 64 * LIMBS - 2);
+            Ok(FixN(wide[0]))
+        }
+    }
+}
+
+impl<const LIMBS: usize> Debug for FixN<LIMBS> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple(&format!("FixN<{}>", LIMBS))
+            .field(&self.to_f64())
+            .finish()
+    }
+}
+
+impl<const LIMBS: usize> Debug for FixNWide<LIMBS> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple(&format!("FixNWide<{}>", LIMBS))
+            .field(&self.0)
+            .finish()
+    }
+}
+
+impl<const LIMBS: usize> Default for FixN<LIMBS> {
+    fn default() -> Self {
+        FixN::zero()
+    }
+}
+
+impl<const LIMBS: usize> Default for FixNWide<LIMBS> {
+    fn default() -> Self {
+        FixNWide::ZERO
+    }
+}
+
+impl<const LIMBS: usize> Ord for FixN<LIMBS> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare(&self.0, &other.0)
+    }
+}
+
+impl<const LIMBS: usize> PartialOrd for FixN<LIMBS> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const LIMBS: usize> Ord for FixNWide<LIMBS> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare(self.0.as_flattened(), other.0.as_flattened())
+    }
+}
+
+impl<const LIMBS: usize> PartialOrd for FixNWide<LIMBS> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const LIMBS: usize> Neg for FixN<LIMBS> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        let mut limbs = self.0;
+        negate(&mut limbs);
+        FixN(limbs)
+    }
+}
+
+impl<const LIMBS: usize> Add for FixN<LIMBS> {
+    type Output = FixResult<Self>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let mut limbs = self.0;
+        if checked_add(&mut limbs, &rhs.0) {
+            Ok(FixN(limbs))
+        } else {
+            Err(FixError::Overflow { op: "FixN::add" })
+        }
+    }
+}
+
+impl<const LIMBS: usize> Sub for FixN<LIMBS> {
+    type Output = FixResult<Self>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let mut limbs = self.0;
+        if checked_sub(&mut limbs, &rhs.0) {
+            Ok(FixN(limbs))
+        } else {
+            Err(FixError::Overflow { op: "FixN::sub" })
+        }
+    }
+}
+
+impl<const LIMBS: usize> Add for FixNWide<LIMBS> {
+    type Output = FixResult<Self>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let mut limbs = self.0;
+        if checked_add(limbs.as_flattened_mut(), rhs.0.as_flattened()) {
+            Ok(FixNWide(limbs))
+        } else {
+            Err(FixError::Overflow {
+                op: "FixNWide::add",
+            })
+        }
+    }
+}
+
+impl<const LIMBS: usize> Sub for FixNWide<LIMBS> {
+    type Output = FixResult<Self>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let mut limbs = self.0;
+        if checked_sub(limbs.as_flattened_mut(), rhs.0.as_flattened()) {
+            Ok(FixNWide(limbs))
+        } else {
+            Err(FixError::Overflow {
+                op: "FixNWide::sub",
+            })
+        }
+    }
+}
+
+impl<const LIMBS: usize> Mul for FixN<LIMBS> {
+    type Output = FixNWide<LIMBS>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let negative = self.is_negative() != rhs.is_negative();
+        let (mut a, mut b) = (self.0, rhs.0);
+        if is_negative(&a) {
+            negate(&mut a);
+        }
+        if is_negative(&b) {
+            negate(&mut b);
+        }
+
+        let mut product = [[0; LIMBS]; 2];
+        let out = product.as_flattened_mut();
+        multiply_magnitudes(&a, &b, out);
+        if negative {
+            negate(out);
+        }
+        FixNWide(product)
+    }
+}
+
+impl<const LIMBS: usize> From<FixN<LIMBS>> for FixNWide<LIMBS> {
+    fn from(val: FixN<LIMBS>) -> Self {
+        let mut limbs = [[if val.is_negative() { u64::MAX } else { 0 }; LIMBS]; 2];
+        limbs.as_flattened_mut()[..LIMBS].copy_from_slice(&val.0);
+        shift_left(limbs.as_flattened_mut(), 64 * LIMBS - 2);
+        FixNWide(limbs)
+    }
+}
+
+impl<const LIMBS: usize> From<Fix2x61> for FixN<LIMBS> {
+    fn from(val: Fix2x61) -> Self {
+        let mut limbs = [0; LIMBS];
+        limbs[LIMBS - 1] = val.0 as u64;
+        FixN(limbs)
+    }
+}
+
+macro_rules! widen_fixn {
+    ($from:literal, $to:literal) => {
+        impl From<FixN<$from>> for FixN<$to> {
+            fn from(val: FixN<$from>) -> Self {
+                val.resize()
+            }
+        }
+    };
+}
+
+widen_fixn!(2, 4);
+widen_fixn!(4, 8);
+widen_fixn!(8, 16);
+
+impl<const LIMBS: usize> TryFrom<i8> for FixN<LIMBS> {
+    type Error = FixError;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        Fix2x61::try_from(value).map(From::from)
+    }
+}
+
+impl<const LIMBS: usize> TryFrom<f64> for FixN<LIMBS> {
+    type Error = FixError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if value.is_nan() || value.abs() >= 4.0 {
+            return Err(FixError::Overflow {
+                op: "FixN::try_from(f64)",
+            });
+        }
+        // Peel off 64 bits at a time, most significant first.  Each step is exact because we only
+        // ever scale by powers of two and subtract the integer part.
+        let mut limbs = [0; LIMBS];
+        let scaled = value * (1u64 << 61) as f64;
+        let top = scaled.floor();
+        limbs[LIMBS - 1] = top as i64 as u64;
+        let mut remainder = scaled - top;
+        for limb in limbs[..LIMBS - 1].iter_mut().rev() {
+            let scaled = remainder * 2f64.powi(64);
+            let whole = scaled.floor();
+            *limb = whole as u64;
+            remainder = scaled - whole;
+        }
+        Ok(FixN(limbs))
+    }
+}
+
+// The rest of the arithmetic works on slices of limbs of any length, so FixV can share it.
+
+pub(super) fn is_negative(limbs: &[u64]) -> bool {
+    limbs[limbs.len() - 1] >> 63 == 1
+}
+
+// Only the most significant limb carries the sign; the rest compare as unsigned.
+pub(super) fn compare(a: &[u64], b: &[u64]) -> Ordering {
+    let top = a.len() - 1;
+    (a[top] as i64)
+        .cmp(&(b[top] as i64))
+        .then_with(|| a[..top].iter().rev().cmp(b[..top].iter().rev()))
+}
+
+pub(super) fn negate(limbs: &mut [u64]) {
+    let mut carry = true;
+    for limb in limbs.iter_mut() {
+        let (sum, overflowed) = (!*limb).overflowing_add(carry as u64);
+        *limb = sum;
+        carry = overflowed;
+    }
+}
+
+// Adds in place, returning false on signed overflow.
+pub(super) fn checked_add(acc: &mut [u64], rhs: &[u64]) -> bool {
+    let signs = (is_negative(acc), is_negative(rhs));
+    let mut carry = false;
+    for (a, &b) in acc.iter_mut().zip(rhs) {
+        let (sum, c1) = a.overflowing_add(b);
+        let (sum, c2) = sum.overflowing_add(carry as u64);
+        *a = sum;
+        carry = c1 || c2;
+    }
+    signs.0 != signs.1 || is_negative(acc) == signs.0
+}
+
+// Subtracts in place, returning false on signed overflow.
+pub(super) fn checked_sub(acc: &mut [u64], rhs: &[u64]) -> bool {
+    let signs = (is_negative(acc), is_negative(rhs));
+    let mut borrow = false;
+    for (a, &b) in acc.iter_mut().zip(rhs) {
+        let (diff, b1) = a.overflowing_sub(b);
+        let (diff, b2) = diff.overflowing_sub(borrow as u64);
+        *a = diff;
+        borrow = b1 || b2;
+    }
+    signs.0 == signs.1 || is_negative(acc) == signs.0
+}
+
+pub(super) fn shift_left(limbs: &mut [u64], bits: usize) {
+    let (words, bits) = (bits / 64, bits % 64);
+    for i in (0..limbs.len()).rev() {
+        let high = if i >= words { limbs[i - words] } else { 0 };
+        let low = if i > words { limbs[i - words - 1] } else { 0 };
+        limbs[i] = if bits == 0 {
+            high
+        } else {
+            (high << bits) | (low >> (64 - bits))
+        };
+    }
+}
+
+// Arithmetic shift: the sign is preserved.
+pub(super) fn shift_right(limbs: &mut [u64], bits: usize) {
+    let fill = if is_negative(limbs) { u64::MAX } else { 0 };
+    let (words, bits) = (bits / 64, bits % 64);
+    let len = limbs.len();
+    for i in 0..len {
+        let low = if i + words < len {
+            limbs[i + words]
+        } else {
+            fill
+        };
+        let high = if i + words + 1 < len {
+            limbs[i + words + 1]
+        } else {
+            fill
+        };
+        limbs[i] = if bits == 0 {
+            low
+        } else {
+            (low >> bits) | (high << (64 - bits))
+        };
+    }
+}
+
+// The product of two magnitudes with the same number of limbs, as a wide value in `out`, which
+// has twice as many.
+pub(super) fn multiply_magnitudes(a: &[u64], b: &[u64], out: &mut [u64]) {
+    let limbs = a.len();
+    for (i, &a) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, &b) in b.iter().enumerate() {
+            let t = a as u128 * b as u128 + out[i + j] as u128 + carry;
+            out[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        out[i + limbs] = carry as u64;
+    }
+    shift_left(out, 1);
+}
+
+// True if a wide value has a magnitude of at least four.
+pub(super) fn exceeds_four(wide: &[u64]) -> bool {
+    let top = wide[wide.len() - 1] as i64;
+    let four = 1i64 << 61;
+    let lower_is_zero = wide[..wide.len() - 1].iter().all(|&l| l == 0);
+    top >= four || top < -four || (top == -four && lower_is_zero)
+}
+
+// floor(n / d) for two wide values, as a narrow one with half as many limbs, or None if it's out
+// of range.
+pub(super) fn divide(n: &[u64], d: &[u64]) -> Option<Vec<u64>> {
+    let limbs = n.len() / 2;
+    // Magnitudes, with a spare limb so the remainder can always be doubled.
+    let magnitude = |wide: &[u64]| {
+        let mut limbs = wide.to_vec();
+        if is_negative(&limbs) {
+            negate(&mut limbs);
+        }
+        limbs.push(0);
+        limbs
+    };
+    let negative = is_negative(n) != is_negative(d);
+    let (n, d) = (magnitude(n), magnitude(d));
+    if d.iter().all(|&l| l == 0) {
+        return None;
+    }
+
+    // The quotient of the raw values needs shifting up by the narrow fractional bits.
+    let shift = 64 * limbs - 3;
+    let mut q = vec![0; limbs + 1];
+    let mut r = vec![0; n.len()];
+    for bit in (0..128 * limbs + shift).rev() {
+        shift_left(&mut r, 1);
+        if bit >= shift {
+            let b = bit - shift;
+            r[0] |= (n[b / 64] >> (b % 64)) & 1;
+        }
+        shift_left(&mut q, 1);
+        if q[limbs] != 0 {
+            return None;
+        }
+        if compare(&r, &d) != Ordering::Less {
+            checked_sub(&mut r, &d);
+            q[0] |= 1;
+        }
+    }
+    if negative && r.iter().any(|&l| l != 0) {
+        let mut one = vec![0; limbs + 1];
+        one[0] = 1;
+        checked_add(&mut q, &one);
+    }
+
+    // Like truncate, anything of magnitude four or more is out of range.
+    if q[limbs] != 0 || q[limbs - 1] >> 63 == 1 {
+        return None;
+    }
+    q.truncate(limbs);
+    if negative {
+        negate(&mut q);
+    }
+    Some(q)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::{TryFrom, TryInto};
+
+    use crate::complex::FixResult;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::fix::fix4x123::Fix4x123;
+    use crate::number::WideNumber;
+
+    use super::{FixN, FixNWide};
+
+    fn wide_to_fix4x123(wide: FixNWide<1>) -> Fix4x123 {
+        Fix4x123::from_bits(((wide.0[1][0] as u128) << 64 | wide.0[0][0] as u128) as i128)
+    }
+
+    #[test]
+    fn single_limb_matches_fix2x61() -> FixResult<()> {
+        let values = [0.5, -0.75, 1.25, -1.9999, 0.0001, -3.5];
+        for &a in values.iter() {
+            for &b in values.iter() {
+                let (fa, fb): (Fix2x61, Fix2x61) = (a.try_into()?, b.try_into()?);
+                let (na, nb): (FixN<1>, FixN<1>) = (fa.into(), fb.into());
+                assert_eq!(
+                    wide_to_fix4x123(na * nb),
+                    fa.widening_mul(fb),
+                    "{} * {}",
+                    a,
+                    b
+                );
+                assert_eq!(
+                    (na * nb).truncate().ok().map(|n| n.0[0] as i64),
+                    fa.widening_mul(fb).truncate().ok().map(|f| f.0)
+                );
+                assert_eq!(
+                    (na + nb).ok().map(|n| n.0[0] as i64),
+                    fa.try_add(fb).ok().map(|f| f.0)
+                );
+                assert_eq!(
+                    (na - nb).ok().map(|n| n.0[0] as i64),
+                    fa.try_sub(fb).ok().map(|f| f.0)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn one_times_one() -> FixResult<()> {
+        let one = FixN::<4>::one();
+        assert_eq!(one * one, FixNWide::one());
+        assert_eq!((one * one).truncate()?, one);
+        Ok(())
+    }
+
+    #[test]
+    fn two_times_two_is_four() {
+        let two = FixN::<3>::two();
+        assert_eq!(two * two, FixNWide::four());
+        assert!((two * two).exceeds_four());
+        assert!((two * two).truncate().is_err());
+        assert!((-two * two).exceeds_four());
+    }
+
+    #[test]
+    fn wide_from_narrow() -> FixResult<()> {
+        let x: FixN<2> = (-1.375).try_into()?;
+        let wide: FixNWide<2> = x.into();
+        assert_eq!(wide.truncate()?, x);
+        assert_eq!(wide, x * FixN::one());
+        Ok(())
+    }
+
+    #[test]
+    fn add_overflows() -> FixResult<()> {
+        let two = FixN::<2>::two();
+        assert!((two + two).is_err());
+        assert!(((-two - two)? - two).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn halve_past_single_limb() -> FixResult<()> {
+        let mut x = FixN::<6>::two();
+        for _ in 0..(64 * 6 - 3) {
+            x = x.halve()?;
+        }
+        assert_eq!(x, FixN::power_of_two(-(64 * 6 - 3) + 1)?);
+        assert!(x.halve()?.halve().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn deep_multiplication() -> FixResult<()> {
+        // 2^-170 * 2^-150 is 2^-320, which would be far below zero in Fix2x61.
+        let a = FixN::<6>::power_of_two(-170)?;
+        let b = FixN::<6>::power_of_two(-150)?;
+        assert_eq!((a * b).truncate()?, FixN::power_of_two(-320)?);
+        assert_eq!((-a * b).truncate()?, -FixN::power_of_two(-320)?);
+        Ok(())
+    }
+
+    #[test]
+    fn negative_product_truncates_like_fix2x61() -> FixResult<()> {
+        let a: FixN<2> = (-0.1).try_into()?;
+        let b: FixN<2> = 0.3.try_into()?;
+        let product = (a * b).truncate()?;
+        assert!(product.is_negative());
+        assert!((product.to_f64() + 0.03).abs() < 1e-15);
+        Ok(())
+    }
+
+    #[test]
+    fn resize_keeps_value() -> FixResult<()> {
+        let x: FixN<2> = (-0.625).try_into()?;
+        let y: FixN<5> = x.resize();
+        assert_eq!(y, FixN::try_from(-0.625)?);
+        assert_eq!(y.resize::<2>(), x);
+        Ok(())
+    }
+
+    #[test]
+    fn float_round_trip() -> FixResult<()> {
+        for &f in [0.0, 1.0, -1.0, 0.1, -2.5e-10, 3.999].iter() {
+            let x: FixN<3> = f.try_into()?;
+            assert_eq!(x.to_f64(), f);
+        }
+        assert!(FixN::<3>::try_from(4.0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn orders_by_value() -> FixResult<()> {
+        let values = [-3.5, -1.0, -2.5e-10, 0.0, 2.5e-10, 0.1, 1.0, 3.999];
+        for (i, &a) in values.iter().enumerate() {
+            for (j, &b) in values.iter().enumerate() {
+                let (na, nb): (FixN<2>, FixN<2>) = (a.try_into()?, b.try_into()?);
+                assert_eq!(na.cmp(&nb), i.cmp(&j), "{} vs {}", a, b);
+                assert_eq!(
+                    (na * na).cmp(&(nb * nb)),
+                    (a * a).partial_cmp(&(b * b)).unwrap()
+                );
+            }
+        }
+        assert!(FixN::<3>::epsilon() > FixN::zero());
+        assert!(-FixN::<3>::epsilon() < FixN::zero());
+        Ok(())
+    }
+
+    #[test]
+    fn division_matches_fix4x123() -> FixResult<()> {
+        let values = [0.0, 0.5, -0.75, 1.25, -1.9999, 0.0001, -3.5];
+        let (f1, n1): (Fix2x61, FixN<1>) = (1.0.try_into()?, 1.0.try_into()?);
+        for &a in values.iter() {
+            for &b in values.iter() {
+                let (fa, fb): (Fix2x61, Fix2x61) = (a.try_into()?, b.try_into()?);
+                let (na, nb): (FixN<1>, FixN<1>) = (fa.into(), fb.into());
+                assert_eq!(
+                    (na * n1)
+                        .checked_div(&(nb * n1))
+                        .ok()
+                        .map(|n| n.0[0] as i64),
+                    fa.widening_mul(f1)
+                        .try_div(fb.widening_mul(f1))
+                        .ok()
+                        .map(|f| f.0),
+                    "{} / {}",
+                    a,
+                    b
+                );
+            }
+        }
+        Ok(())
+    }
+}
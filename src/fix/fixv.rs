@@ -0,0 +1,355 @@
+use std::borrow::Cow;
+use std::cmp::max;
+use std::fmt::{Debug, Formatter};
+use std::ops::Neg;
+
+use crate::complex::{FixError, FixResult};
+use crate::fix::fixn::{
+    checked_add, checked_sub, divide, exceeds_four, is_negative, multiply_magnitudes, negate,
+    shift_left, shift_right, FixN,
+};
+
+// FixN with the number of limbs chosen as we go, for zooms deeper than any FixN we build in.
+// Limbs are stored least significant first, as in FixN.  A value with fewer limbs than another
+// is treated as if it had zero limbs added at the bottom, so results have as many limbs as the
+// longest operand, and constants only need one.
+#[derive(Clone)]
+pub struct FixV(Cow<'static, [u64]>);
+
+// The product of two FixVs with n limbs: 2n limbs, laid out as FixNWide<n>.
+#[derive(Clone)]
+pub struct FixVWide(Vec<u64>);
+
+impl FixV {
+    pub const ZERO: FixV = FixV(Cow::Borrowed(&[0]));
+    pub const ONE: FixV = FixV(Cow::Borrowed(&[1 << 61]));
+    pub const TWO: FixV = FixV(Cow::Borrowed(&[1 << 62]));
+
+    pub fn limbs(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn fractional_bits(&self) -> u32 {
+        64 * self.limbs() as u32 - 3
+    }
+
+    // The same value with at least `limbs` limbs.
+    pub fn extend(&self, limbs: usize) -> FixV {
+        FixV(Cow::Owned(extended(&self.0, limbs)))
+    }
+
+    // 2^pow, with as few limbs as that needs.
+    pub fn power_of_two(pow: i32) -> FixResult<FixV> {
+        if pow > 1 {
+            return Err(FixError::Overflow {
+                op: "FixV::power_of_two",
+            });
+        }
+        let limbs = max(1, (3 - pow as i64 + 63) / 64) as usize;
+        let bit = (64 * limbs as i64 - 3 + pow as i64) as usize;
+        let mut value = vec![0; limbs];
+        value[bit / 64] = 1 << (bit % 64);
+        Ok(FixV(Cow::Owned(value)))
+    }
+
+    pub fn halve(&self) -> FixResult<FixV> {
+        let mut limbs = self.0.to_vec();
+        shift_right(&mut limbs, 1);
+        if limbs.iter().all(|&l| l == 0) {
+            Err(FixError::Underflow { op: "FixV::halve" })
+        } else {
+            Ok(FixV(Cow::Owned(limbs)))
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        is_negative(&self.0)
+    }
+
+    fn magnitude(&self) -> Vec<u64> {
+        let mut magnitude = self.0.to_vec();
+        if self.is_negative() {
+            negate(&mut magnitude);
+        }
+        magnitude
+    }
+
+    // floor(log2(|self|)), or None for zero.
+    pub fn exponent(&self) -> Option<i32> {
+        let magnitude = self.magnitude();
+        let (i, top) = magnitude.iter().enumerate().rev().find(|(_, &l)| l != 0)?;
+        Some((64 * i + 63 - top.leading_zeros() as usize) as i32 - self.fractional_bits() as i32)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let value = self
+            .magnitude()
+            .iter()
+            .enumerate()
+            .map(|(i, &l)| l as f64 * 2f64.powi(64 * i as i32 - self.fractional_bits() as i32))
+            .sum::<f64>();
+        if self.is_negative() {
+            -value
+        } else {
+            value
+        }
+    }
+
+    // The nearest value to an f64 is the f64 itself, so this only fails outside [-4, 4).  It
+    // uses as many limbs as it takes to be exact.
+    pub fn from_f64(value: f64) -> FixResult<FixV> {
+        if value.is_nan() || value.abs() >= 4.0 {
+            return Err(FixError::Overflow {
+                op: "FixV::from_f64",
+            });
+        }
+        // As FixN does it, most significant limb first, but stopping once there's nothing left.
+        let scaled = value * (1u64 << 61) as f64;
+        let top = scaled.floor();
+        let mut limbs = vec![top as i64 as u64];
+        let mut remainder = scaled - top;
+        while remainder != 0.0 {
+            let scaled = remainder * 2f64.powi(64);
+            let whole = scaled.floor();
+            limbs.push(whole as u64);
+            remainder = scaled - whole;
+        }
+        limbs.reverse();
+        Ok(FixV(Cow::Owned(limbs)))
+    }
+
+    pub fn try_add(self, rhs: FixV) -> FixResult<FixV> {
+        let (mut a, b) = aligned(&self.0, &rhs.0);
+        if checked_add(&mut a, &b) {
+            Ok(FixV(Cow::Owned(a)))
+        } else {
+            Err(FixError::Overflow { op: "FixV::add" })
+        }
+    }
+
+    pub fn try_sub(self, rhs: FixV) -> FixResult<FixV> {
+        let (mut a, b) = aligned(&self.0, &rhs.0);
+        if checked_sub(&mut a, &b) {
+            Ok(FixV(Cow::Owned(a)))
+        } else {
+            Err(FixError::Overflow { op: "FixV::sub" })
+        }
+    }
+
+    pub fn widen(&self) -> FixVWide {
+        let limbs = self.limbs();
+        let mut wide = vec![if self.is_negative() { u64::MAX } else { 0 }; 2 * limbs];
+        wide[..limbs].copy_from_slice(&self.0);
+        shift_left(&mut wide, 64 * limbs - 2);
+        FixVWide(wide)
+    }
+
+    pub fn widening_mul(self, rhs: FixV) -> FixVWide {
+        let negative = self.is_negative() != rhs.is_negative();
+        let limbs = max(self.limbs(), rhs.limbs());
+        let (a, b) = (
+            extended(&self.magnitude(), limbs),
+            extended(&rhs.magnitude(), limbs),
+        );
+        let mut product = vec![0; 2 * limbs];
+        multiply_magnitudes(&a, &b, &mut product);
+        if negative {
+            negate(&mut product);
+        }
+        FixVWide(product)
+    }
+}
+
+impl FixVWide {
+    pub fn exceeds_four(&self) -> bool {
+        exceeds_four(&self.0)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        is_negative(&self.0)
+    }
+
+    pub fn try_add(self, rhs: FixVWide) -> FixResult<FixVWide> {
+        let (mut a, b) = aligned(&self.0, &rhs.0);
+        if checked_add(&mut a, &b) {
+            Ok(FixVWide(a))
+        } else {
+            Err(FixError::Overflow {
+                op: "FixVWide::add",
+            })
+        }
+    }
+
+    pub fn try_sub(self, rhs: FixVWide) -> FixResult<FixVWide> {
+        let (mut a, b) = aligned(&self.0, &rhs.0);
+        if checked_sub(&mut a, &b) {
+            Ok(FixVWide(a))
+        } else {
+            Err(FixError::Overflow {
+                op: "FixVWide::sub",
+            })
+        }
+    }
+
+    pub fn truncate(&self) -> FixResult<FixV> {
+        if self.exceeds_four() {
+            Err(FixError::Overflow {
+                op: "FixV::truncate",
+            })
+        } else {
+            let limbs = self.0.len() / 2;
+            let mut wide = self.0.clone();
+            shift_right(&mut wide, 64 * limbs - 2);
+            wide.truncate(limbs);
+            Ok(FixV(Cow::Owned(wide)))
+        }
+    }
+
+    // floor(self / rhs), by long division one bit at a time.
+    pub fn checked_div(&self, rhs: &FixVWide) -> FixResult<FixV> {
+        let (n, d) = aligned(&self.0, &rhs.0);
+        divide(&n, &d)
+            .map(|q| FixV(Cow::Owned(q)))
+            .ok_or(FixError::Overflow {
+                op: "FixVWide::checked_div",
+            })
+    }
+}
+
+// The limbs with zero limbs added at the bottom to make up the length.
+fn extended(limbs: &[u64], length: usize) -> Vec<u64> {
+    let mut result = vec![0; length.saturating_sub(limbs.len())];
+    result.extend_from_slice(limbs);
+    result
+}
+
+// Both values with the same number of limbs.  Wide values always have an even number, so this
+// keeps their layout too.
+fn aligned(a: &[u64], b: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let length = max(a.len(), b.len());
+    (extended(a, length), extended(b, length))
+}
+
+impl Debug for FixV {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple(&format!("FixV<{}>", self.limbs()))
+            .field(&self.to_f64())
+            .finish()
+    }
+}
+
+impl Debug for FixVWide {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FixVWide").field(&self.0).finish()
+    }
+}
+
+impl Default for FixV {
+    fn default() -> Self {
+        FixV::ZERO
+    }
+}
+
+// Equal values are equal however many limbs they have.
+impl PartialEq for FixV {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = aligned(&self.0, &other.0);
+        a == b
+    }
+}
+
+impl Neg for FixV {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        let mut limbs = self.0.into_owned();
+        negate(&mut limbs);
+        FixV(Cow::Owned(limbs))
+    }
+}
+
+impl<const LIMBS: usize> From<FixN<LIMBS>> for FixV {
+    fn from(val: FixN<LIMBS>) -> Self {
+        FixV(Cow::Owned(val.0.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::complex::FixResult;
+    use crate::fix::fixn::FixN;
+    use crate::number::{MandelbrotNumber, WideNumber};
+
+    use super::FixV;
+
+    #[test]
+    fn matches_fixn() -> FixResult<()> {
+        let values = [0.5, -0.75, 1.25, -1.9999, 0.0001, -3.5, 1e-30];
+        for &a in values.iter() {
+            for &b in values.iter() {
+                let (na, nb) = (FixN::<3>::try_from(a)?, FixN::<3>::try_from(b)?);
+                let (va, vb) = (FixV::from(na), FixV::from(nb));
+                let fixv = |n: FixN<3>| FixV::from(n);
+                assert_eq!(
+                    va.clone().widening_mul(vb.clone()).truncate().ok(),
+                    na.widening_mul(nb).truncate().ok().map(fixv),
+                    "{} * {}",
+                    a,
+                    b
+                );
+                assert_eq!(
+                    va.clone().try_add(vb.clone()).ok(),
+                    na.try_add(nb).ok().map(fixv)
+                );
+                assert_eq!(
+                    va.clone().try_sub(vb.clone()).ok(),
+                    na.try_sub(nb).ok().map(fixv)
+                );
+                assert_eq!(
+                    va.widen().try_div(vb.widen()).ok(),
+                    na.widen().try_div(nb.widen()).ok().map(fixv)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn short_values_extend() -> FixResult<()> {
+        let deep = FixV::power_of_two(-1000)?;
+        assert_eq!(deep.limbs(), 16);
+        assert_eq!(deep.exponent(), Some(-1000));
+        let sum = FixV::ONE.try_add(deep.clone())?;
+        assert_eq!(sum.limbs(), 16);
+        assert_eq!(sum.try_sub(FixV::ONE)?, deep);
+        assert_eq!(FixV::ONE.extend(4), FixV::ONE);
+        assert_eq!(
+            FixV::TWO.widening_mul(deep).truncate()?,
+            FixV::power_of_two(-999)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn halves_down_to_the_last_limb() -> FixResult<()> {
+        let mut x = FixV::ONE.extend(20);
+        for _ in 0..(64 * 20 - 3) {
+            x = x.halve()?;
+        }
+        assert_eq!(x, FixV::power_of_two(-(64 * 20 - 3))?);
+        assert!(x.halve().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn float_round_trip() -> FixResult<()> {
+        for &f in [0.0, 1.0, -1.0, 0.1, -2.5e-10, 3.999, 1e-30].iter() {
+            assert_eq!(FixV::from_f64(f)?.to_f64(), f);
+        }
+        assert_eq!(FixV::from_f64(0.5)?.limbs(), 1);
+        assert!(FixV::from_f64(4.0).is_err());
+        Ok(())
+    }
+}
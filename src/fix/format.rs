@@ -0,0 +1,314 @@
+use std::fmt::{Formatter, Result as FmtResult};
+
+use anyhow::{bail, Error};
+
+use crate::extending::widening_mul_u128;
+
+// Exact conversion between fixed point values and strings.  Values are handled as a sign and a
+// magnitude with `frac_bits` fractional bits, which is enough for anything that fits in an i128.
+// Every binary fraction has a terminating decimal expansion, so printing is always exact;
+// parsing rounds to the nearest representable value, with ties going to even.
+
+pub(crate) fn write_fixed(
+    f: &mut Formatter<'_>,
+    negative: bool,
+    magnitude: u128,
+    frac_bits: u32,
+    base: u32,
+) -> FmtResult {
+    let mask = (1u128 << frac_bits) - 1;
+    let mut digits = integer_digits(magnitude >> frac_bits, base);
+
+    // The remainder can use all 128 bits, so each digit comes from the top half of a 256 bit
+    // product.
+    let mut fraction = vec![];
+    let mut remainder = magnitude & mask;
+    while remainder != 0 {
+        let (hi, lo) = widening_mul_u128(remainder, base as u128);
+        fraction.push(((hi << (128 - frac_bits)) | (lo >> frac_bits)) as u8);
+        remainder = lo & mask;
+    }
+
+    if let Some(precision) = f.precision() {
+        if fraction.len() > precision {
+            let round_up = fraction[precision] as u32 * 2 >= base;
+            fraction.truncate(precision);
+            if round_up && increment(&mut fraction, base) {
+                increment(&mut digits, base);
+                if digits[0] == 0 {
+                    digits.insert(0, 1);
+                }
+            }
+        }
+        fraction.resize(precision, 0);
+    }
+
+    let mut text: String = digits.iter().map(|&d| to_char(d)).collect();
+    if !fraction.is_empty() {
+        text.push('.');
+        text.extend(fraction.iter().map(|&d| to_char(d)));
+    }
+    let prefix = match base {
+        2 => "0b",
+        16 => "0x",
+        _ => "",
+    };
+    f.pad_integral(!negative || magnitude == 0, prefix, &text)
+}
+
+// Parses decimal, or hex and binary with a 0x or 0b prefix, into a value with `frac_bits`
+// fractional bits whose magnitude is below 2^int_bits (or equal to it, for negative values).  An
+// explicit radix takes the place of a prefix.
+pub(crate) fn parse_fixed(
+    s: &str,
+    radix: Option<u32>,
+    frac_bits: u32,
+    int_bits: u32,
+) -> Result<i128, Error> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (base, digits) = match radix {
+        Some(radix) if (2..=36).contains(&radix) => (radix, unsigned),
+        Some(radix) => bail!("Radix {} is not supported", radix),
+        None => {
+            if let Some(rest) = unsigned.strip_prefix("0x") {
+                (16, rest)
+            } else if let Some(rest) = unsigned.strip_prefix("0b") {
+                (2, rest)
+            } else {
+                (10, unsigned)
+            }
+        }
+    };
+
+    // Decimals may have an exponent, which we apply by moving the point.
+    let (digits, exponent) = match digits.split_once(['e', 'E']) {
+        Some((digits, exponent)) if base == 10 => match exponent.parse::<i32>() {
+            Ok(exponent) if exponent.abs() <= 1000 => (digits, exponent),
+            _ => bail!("'{}' has an invalid exponent", s),
+        },
+        _ => (digits, 0),
+    };
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, fraction),
+        None => (digits, ""),
+    };
+    if integer.is_empty() && fraction.is_empty() {
+        bail!("'{}' has no digits", s);
+    }
+    let shifted;
+    let (integer, fraction) = if exponent == 0 {
+        (integer, fraction)
+    } else {
+        let point = integer.len() as i32 + exponent;
+        let all = format!(
+            "{}{}{}{}",
+            "0".repeat((-point).max(0) as usize),
+            integer,
+            fraction,
+            "0".repeat((point - (integer.len() + fraction.len()) as i32).max(0) as usize)
+        );
+        shifted = all;
+        shifted.split_at(point.max(0) as usize)
+    };
+    let parse_digits = |part: &str| -> Result<Vec<u8>, Error> {
+        part.chars()
+            .enumerate()
+            .map(|(i, c)| match c.to_digit(base) {
+                Some(d) => Ok(d as u8),
+                None => bail!("'{}' is not a base {} digit in '{}' at {}", c, base, s, i),
+            })
+            .collect()
+    };
+    let integer = parse_digits(integer)?;
+    let mut fraction = parse_digits(fraction)?;
+
+    let limit = 1u128 << int_bits;
+    let mut whole = 0u128;
+    for d in integer {
+        whole = whole * base as u128 + d as u128;
+        if whole > limit {
+            bail!(
+                "'{}' is out of range: must be less than {} in magnitude",
+                s,
+                limit
+            );
+        }
+    }
+
+    // Long multiplication of the fractional digits by two, one bit at a time.
+    let mut double = || -> bool {
+        let mut carry = 0;
+        for d in fraction.iter_mut().rev() {
+            let v = *d as u32 * 2 + carry;
+            *d = (v % base) as u8;
+            carry = v / base;
+        }
+        carry != 0
+    };
+    let mut bits = 0u128;
+    for _ in 0..frac_bits {
+        bits = (bits << 1) | double() as u128;
+    }
+    let half = double();
+    let beyond_half = fraction.iter().any(|&d| d != 0);
+    if half && (beyond_half || bits & 1 == 1) {
+        bits += 1;
+    }
+
+    let magnitude = (whole << frac_bits) + bits;
+    let limit = limit << frac_bits;
+    if magnitude > limit || (magnitude == limit && !negative) {
+        bail!(
+            "'{}' is out of range: must be less than {} in magnitude",
+            s,
+            1u128 << int_bits
+        );
+    }
+    Ok(if negative {
+        (magnitude as i128).wrapping_neg()
+    } else {
+        magnitude as i128
+    })
+}
+
+fn integer_digits(mut value: u128, base: u32) -> Vec<u8> {
+    let mut digits = vec![];
+    loop {
+        digits.push((value % base as u128) as u8);
+        value /= base as u128;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    digits
+}
+
+// Adds one to the last digit, returning true if it carried out of the first.
+fn increment(digits: &mut [u8], base: u32) -> bool {
+    for d in digits.iter_mut().rev() {
+        *d += 1;
+        if (*d as u32) < base {
+            return false;
+        }
+        *d = 0;
+    }
+    true
+}
+
+fn to_char(digit: u8) -> char {
+    std::char::from_digit(digit as u32, 36).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::fix::fix4x123::Fix4x123;
+
+    const BITS: [i64; 8] = [
+        0,
+        1,
+        -1,
+        0x1234_5678_9abc_def1,
+        -0x7fff_ffff_ffff_ffff,
+        1 << 61,
+        i64::MAX,
+        i64::MIN,
+    ];
+
+    #[test]
+    fn prints_exactly() {
+        assert_eq!(Fix2x61::from_bits(3 << 59).to_string(), "0.75");
+        assert_eq!(Fix2x61::from_bits(-5 << 59).to_string(), "-1.25");
+        assert_eq!(Fix2x61::from_bits(0).to_string(), "0");
+        assert_eq!(Fix2x61::from_bits(1).to_string().len(), "0.".len() + 61);
+        assert_eq!(Fix2x61::from_bits(i64::MIN).to_string(), "-4");
+        assert_eq!(format!("{:x}", Fix2x61::from_bits(3 << 60)), "1.8");
+        assert_eq!(format!("{:#b}", Fix2x61::from_bits(-3 << 59)), "-0b0.11");
+        assert_eq!(format!("{:.3}", Fix2x61::from_bits(5 << 58)), "0.625");
+        assert_eq!(format!("{:.2}", Fix2x61::from_bits(5 << 58)), "0.63");
+        assert_eq!(format!("{:.1}", Fix2x61::from_bits((1 << 62) - 1)), "2.0");
+        assert_eq!(format!("{:>6}", Fix2x61::from_bits(1 << 60)), "   0.5");
+    }
+
+    #[test]
+    fn round_trips() -> Result<(), Error> {
+        for &bits in BITS.iter() {
+            let fix = Fix2x61::from_bits(bits);
+            assert_eq!(Fix2x61::from_str(&fix.to_string())?, fix);
+            assert_eq!(Fix2x61::from_str(&format!("{:#x}", fix))?, fix);
+            assert_eq!(Fix2x61::from_str(&format!("{:#b}", fix))?, fix);
+
+            let wide = Fix4x123::from(fix).checked_add(Fix4x123::from_bits(bits as i128 >> 3));
+            if let Some(wide) = wide {
+                assert_eq!(Fix4x123::from_str(&wide.to_string())?, wide);
+                assert_eq!(Fix4x123::from_str(&format!("{:#x}", wide))?, wide);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rounds_to_nearest() -> Result<(), Error> {
+        // 0.1 * 2^61 = 230584300921369395.2
+        assert_eq!(
+            Fix2x61::from_str("0.1")?,
+            Fix2x61::from_bits(230584300921369395)
+        );
+        assert_eq!(
+            Fix2x61::from_str("-0.1")?,
+            Fix2x61::from_bits(-230584300921369395)
+        );
+        // Exactly half way between 0 and 2^-61 rounds to even, anything more rounds up.
+        let half_ulp = Fix4x123::from_bits(1 << 61).to_string();
+        assert_eq!(Fix2x61::from_str(&half_ulp)?, Fix2x61::from_bits(0));
+        assert_eq!(
+            Fix2x61::from_str(&format!("{}1", half_ulp))?,
+            Fix2x61::from_bits(1)
+        );
+        assert_eq!(Fix2x61::from_str("1e-1")?, Fix2x61::from_str("0.1")?);
+        assert_eq!(Fix2x61::from_str("0.0125E2")?, Fix2x61::from_bits(5 << 59));
+        assert_eq!(Fix2x61::from_str("125e-3")?, Fix2x61::from_bits(1 << 58));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_nonsense() {
+        for s in [
+            "",
+            "-",
+            ".",
+            "1.2.3",
+            "0x1g",
+            "4",
+            "-4.0000001",
+            "0b2",
+            "1e",
+            "abc",
+        ]
+        .iter()
+        {
+            assert!(Fix2x61::from_str(s).is_err(), "{}", s);
+        }
+        assert!(Fix2x61::from_str("-4").is_ok());
+        assert!(Fix4x123::from_str("15.5").is_ok());
+        assert!(Fix4x123::from_str("16").is_err());
+    }
+
+    #[test]
+    fn complex_round_trips() -> Result<(), Error> {
+        let c = Complex::from_str("-0.7453,0.1127")?;
+        assert_eq!(Complex::from_str(&c.to_string())?, c);
+        assert_eq!(c.r, Fix2x61::from_str("-0.7453")?);
+        assert!(Complex::from_str("0.5").is_err());
+        Ok(())
+    }
+}
@@ -1,2 +1,7 @@
+pub mod fix2x29;
 pub mod fix2x61;
 pub mod fix4x123;
+pub mod fixn;
+pub mod fixv;
+pub(crate) mod format;
+mod ops;
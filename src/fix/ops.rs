@@ -0,0 +1,431 @@
+use std::ops::{Add, Div, Mul, Neg, Rem, Shr, Sub};
+
+use num_traits::{
+    Bounded, CheckedAdd, CheckedDiv, CheckedMul, CheckedNeg, CheckedSub, Num, One, Signed,
+    WrappingAdd, WrappingMul, WrappingSub, Zero,
+};
+
+use crate::complex::{FixError, FixResult};
+use crate::extending::{parse, Fixed, FixedStorage};
+
+// The numeric surface of a fixed point type, built on the handful of wider operations its
+// FixedStorage provides.
+//
+// num_traits needs +, -, *, /, %, unary - and abs to return Self, so those behave like the integer
+// operations and panic on overflow.  Nothing else panics: iteration wants overflow as an error it
+// can turn into an escape, so it goes through try_add and friends, and everything beyond what
+// num_traits needs comes in checked form only.
+impl<T: FixedStorage, const FRAC: u32> Fixed<T, FRAC> {
+    pub const MIN: Self = Fixed(T::MIN);
+    pub const MAX: Self = Fixed(T::MAX);
+    pub const EPSILON: Self = Fixed(T::ONE);
+
+    #[inline]
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(&rhs.0).map(Fixed)
+    }
+
+    #[inline]
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(&rhs.0).map(Fixed)
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_mul(rhs) {
+            (product, false) => Some(product),
+            (_, true) => None,
+        }
+    }
+
+    // Rounds towards negative infinity.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        self.0.checked_div_shift(rhs.0, FRAC).map(Fixed)
+    }
+
+    pub fn checked_rem(self, rhs: Self) -> Option<Self> {
+        self.0.checked_rem(&rhs.0).map(Fixed)
+    }
+
+    pub fn checked_neg(self) -> Option<Self> {
+        self.0.checked_neg().map(Fixed)
+    }
+
+    pub fn checked_abs(self) -> Option<Self> {
+        if self.0 < T::ZERO {
+            self.checked_neg()
+        } else {
+            Some(self)
+        }
+    }
+
+    // Rounds down, and fails for negative numbers.
+    pub fn checked_sqrt(self) -> Option<Self> {
+        self.0.checked_sqrt_shift(FRAC).map(Fixed)
+    }
+
+    // Multiplies by 2^n, failing rather than losing any significant bits.
+    pub fn checked_shl(self, n: u32) -> Option<Self> {
+        if n >= T::BITS {
+            return if self.0.is_zero() { Some(self) } else { None };
+        }
+        let shifted = self.0 << n as usize;
+        if shifted >> n as usize == self.0 {
+            Some(Fixed(shifted))
+        } else {
+            None
+        }
+    }
+
+    // Rounds towards negative infinity.
+    pub fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+        let (product, overflow) = self.0.overflowing_mul_shift(rhs.0, FRAC);
+        (Fixed(product), overflow)
+    }
+
+    pub fn wrapping_add(self, rhs: Self) -> Self {
+        Fixed(self.0.wrapping_add(&rhs.0))
+    }
+
+    pub fn wrapping_sub(self, rhs: Self) -> Self {
+        Fixed(self.0.wrapping_sub(&rhs.0))
+    }
+
+    pub fn wrapping_mul(self, rhs: Self) -> Self {
+        self.overflowing_mul(rhs).0
+    }
+
+    pub fn wrapping_neg(self) -> Self {
+        Fixed(self.0.wrapping_neg())
+    }
+
+    #[inline]
+    pub fn try_add(self, rhs: Self) -> FixResult<Self> {
+        self.checked_add(rhs)
+            .ok_or(FixError::Overflow { op: "Fixed::add" })
+    }
+
+    #[inline]
+    pub fn try_sub(self, rhs: Self) -> FixResult<Self> {
+        self.checked_sub(rhs)
+            .ok_or(FixError::Overflow { op: "Fixed::sub" })
+    }
+
+    pub fn try_mul(self, rhs: Self) -> FixResult<Self> {
+        self.checked_mul(rhs)
+            .ok_or(FixError::Overflow { op: "Fixed::mul" })
+    }
+
+    pub fn abs(self) -> Self {
+        self.checked_abs().expect("Fixed::abs overflowed")
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < T::ZERO
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > T::ZERO
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Neg for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.checked_neg().expect("Fixed::neg overflowed")
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Add for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs).expect("Fixed::add overflowed")
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Sub for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs).expect("Fixed::sub overflowed")
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Mul for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(rhs).expect("Fixed::mul overflowed")
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Div for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs)
+            .expect("Fixed::div overflowed or divided by zero")
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Rem for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self::Output {
+        self.checked_rem(rhs).expect("Fixed::rem divided by zero")
+    }
+}
+
+// Divides by 2^n, rounding towards negative infinity.
+impl<T: FixedStorage, const FRAC: u32> Shr<u32> for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn shr(self, n: u32) -> Self::Output {
+        Fixed(self.0 >> n.min(T::BITS - 1) as usize)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Zero for Fixed<T, FRAC> {
+    fn zero() -> Self {
+        Self::ZERO
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> One for Fixed<T, FRAC> {
+    fn one() -> Self {
+        Fixed(T::one() << FRAC as usize)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Num for Fixed<T, FRAC> {
+    type FromStrRadixErr = anyhow::Error;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        parse(s, Some(radix))
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Signed for Fixed<T, FRAC> {
+    fn abs(&self) -> Self {
+        Fixed::abs(*self)
+    }
+
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self <= other {
+            Self::ZERO
+        } else {
+            *self - *other
+        }
+    }
+
+    fn signum(&self) -> Self {
+        if self.0.is_zero() {
+            Self::ZERO
+        } else if self.is_positive() {
+            Self::one()
+        } else {
+            -Self::one()
+        }
+    }
+
+    fn is_positive(&self) -> bool {
+        Fixed::is_positive(self)
+    }
+
+    fn is_negative(&self) -> bool {
+        Fixed::is_negative(self)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Bounded for Fixed<T, FRAC> {
+    fn min_value() -> Self {
+        Self::MIN
+    }
+
+    fn max_value() -> Self {
+        Self::MAX
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> CheckedAdd for Fixed<T, FRAC> {
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Fixed::checked_add(*self, *rhs)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> CheckedSub for Fixed<T, FRAC> {
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        Fixed::checked_sub(*self, *rhs)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> CheckedMul for Fixed<T, FRAC> {
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Fixed::checked_mul(*self, *rhs)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> CheckedDiv for Fixed<T, FRAC> {
+    fn checked_div(&self, rhs: &Self) -> Option<Self> {
+        Fixed::checked_div(*self, *rhs)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> CheckedNeg for Fixed<T, FRAC> {
+    fn checked_neg(&self) -> Option<Self> {
+        Fixed::checked_neg(*self)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> WrappingAdd for Fixed<T, FRAC> {
+    fn wrapping_add(&self, rhs: &Self) -> Self {
+        Fixed::wrapping_add(*self, *rhs)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> WrappingSub for Fixed<T, FRAC> {
+    fn wrapping_sub(&self, rhs: &Self) -> Self {
+        Fixed::wrapping_sub(*self, *rhs)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> WrappingMul for Fixed<T, FRAC> {
+    fn wrapping_mul(&self, rhs: &Self) -> Self {
+        Fixed::wrapping_mul(*self, *rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+    use num_traits::{Bounded, CheckedMul, Num, One, Signed, Zero};
+
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::fix::fix4x123::Fix4x123;
+
+    const VALUES: [f64; 7] = [0.5, -0.75, 1.25, -1.9999, 0.0001, -0.3, 1.7];
+
+    fn norm_sqr<T: Num + Copy>(r: T, i: T) -> T {
+        r * r + i * i
+    }
+
+    #[test]
+    fn agrees_with_f64() -> Result<(), Error> {
+        for &a in VALUES.iter() {
+            for &b in VALUES.iter() {
+                let (fa, fb) = (Fix2x61::try_from(a)?, Fix2x61::try_from(b)?);
+                let (a, b) = (fa.to_f64(), fb.to_f64());
+                assert!(((fa * fb).to_f64() - a * b).abs() < 1e-15, "{} * {}", a, b);
+                if (a / b).abs() < 4.0 {
+                    assert!(((fa / fb).to_f64() - a / b).abs() < 1e-12, "{} / {}", a, b);
+                } else {
+                    assert_eq!(fa.checked_div(fb), None);
+                }
+                assert_eq!(fa < fb, a < b);
+                assert_eq!(fa.abs().to_f64(), a.abs());
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn wide_arithmetic_is_exact() -> Result<(), Error> {
+        for &a in VALUES.iter() {
+            for &b in VALUES.iter() {
+                let (fa, fb) = (Fix2x61::try_from(a)?, Fix2x61::try_from(b)?);
+                let (wa, wb) = (Fix4x123::from(fa), Fix4x123::from(fb));
+                let product = fa.widening_mul(fb);
+                assert_eq!(wa * wb, product, "{} * {}", a, b);
+                assert_eq!(product / wb, wa, "{} * {} / {}", a, b, b);
+                assert_eq!(wa.cmp(&wb), fa.cmp(&fb));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rounds_towards_negative_infinity() {
+        let tiny = Fix2x61::EPSILON;
+        assert_eq!(tiny * Fix2x61::power_of_two(-1).unwrap(), Fix2x61::zero());
+        assert_eq!(-tiny * Fix2x61::power_of_two(-1).unwrap(), -tiny);
+        assert_eq!(-tiny / Fix2x61::two(), -tiny);
+        assert_eq!(-tiny >> 64, -tiny);
+        assert_eq!(-Fix4x123::EPSILON / Fix4x123::two(), -Fix4x123::EPSILON);
+        assert_eq!(
+            -Fix4x123::EPSILON * (Fix4x123::one() >> 1),
+            -Fix4x123::EPSILON
+        );
+    }
+
+    #[test]
+    fn square_roots() -> Result<(), Error> {
+        let sqrt = |x: Fix2x61| x.checked_sqrt().unwrap();
+        let wide_sqrt = |x: Fix4x123| x.checked_sqrt().unwrap();
+        assert_eq!(sqrt(Fix2x61::try_from(2.25)?), Fix2x61::try_from(1.5)?);
+        assert_eq!(
+            wide_sqrt(Fix4x123::from(Fix2x61::try_from(2.25)?)),
+            Fix4x123::from(Fix2x61::try_from(1.5)?)
+        );
+        let two = Fix2x61::two();
+        let root = sqrt(two);
+        let next = root + Fix2x61::EPSILON;
+        assert!(root.widening_mul(root) <= two.into() && next.widening_mul(next) > two.into());
+        let two = Fix4x123::two();
+        let root = wide_sqrt(two);
+        // Products round down, so the next value up may only reach two.
+        let next = root + Fix4x123::EPSILON;
+        assert!(root * root < two && next * next >= two);
+        assert_eq!((-Fix2x61::one()).checked_sqrt(), None);
+        assert_eq!(wide_sqrt(Fix4x123::MAX).to_string()[..6], *"3.9999");
+        Ok(())
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(Fix2x61::MAX.checked_add(Fix2x61::EPSILON), None);
+        assert_eq!(Fix2x61::MAX.wrapping_add(Fix2x61::EPSILON), Fix2x61::MIN);
+        assert_eq!(Fix2x61::MIN.checked_abs(), None);
+        assert_eq!(Fix2x61::two().checked_mul(Fix2x61::two()), None);
+        let eight = Fix4x123::four().checked_shl(1).unwrap();
+        assert_eq!(Fix4x123::two().checked_mul(eight), None);
+        assert_eq!(Fix4x123::one().checked_div(Fix4x123::ZERO), None);
+        assert_eq!(Fix4x123::four().checked_div(Fix4x123::one() >> 2), None);
+        assert_eq!(Fix2x61::one().checked_shl(2), None);
+        assert_eq!(Fix2x61::one().checked_shl(1), Some(Fix2x61::two()));
+        assert!(Fix2x61::try_add(Fix2x61::MAX, Fix2x61::MAX).is_err());
+    }
+
+    #[test]
+    fn num_traits() -> Result<(), Error> {
+        let three_quarters = Fix2x61::try_from(0.75)?;
+        assert_eq!(
+            norm_sqr(three_quarters, three_quarters),
+            Fix2x61::try_from(1.125)?
+        );
+        assert_eq!(
+            Fix2x61::from_str_radix("-1.1", 2)?,
+            Fix2x61::try_from(-1.5)?
+        );
+        assert_eq!(
+            Fix4x123::from_str_radix("0.c", 16)?,
+            Fix4x123::from(three_quarters)
+        );
+        assert!(<Fix2x61 as Zero>::zero().is_zero());
+        assert!(<Fix4x123 as One>::one().is_one());
+        assert_eq!(Signed::signum(&-three_quarters), -Fix2x61::one());
+        assert_eq!(
+            Signed::abs_sub(&Fix2x61::one(), &three_quarters),
+            Fix2x61::try_from(0.25)?
+        );
+        assert_eq!(<Fix2x61 as Bounded>::max_value(), Fix2x61::MAX);
+        assert_eq!(CheckedMul::checked_mul(&Fix2x61::MAX, &Fix2x61::MAX), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,391 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+
+use crate::complex::{Complex, FixResult};
+use crate::expression::Compiled;
+use crate::newton::Polynomial;
+use crate::number::MandelbrotNumber;
+
+// One step of an orbit.  Alongside z, each point keeps some state of its own for formulas that
+// need it, which starts at zero.  Reports escape as Err(Escaped) and settling on an attractor as
+// Err(Converged) with an index for which attractor, either of which finishes the point.
+pub trait Iteration {
+    fn iterate<N: MandelbrotNumber>(
+        &self,
+        z: &mut Complex<N>,
+        state: &mut Complex<N>,
+        c: &Complex<N>,
+    ) -> FixResult<()>;
+}
+
+// z = z^2 + Re(c) + Im(c) * previous z, which is kept in the state.
+pub struct Phoenix;
+
+// Iteration of the renormalisation maps from the Ising model, which converge on 1 inside.
+pub struct MagnetI;
+pub struct MagnetII;
+
+impl Iteration for Phoenix {
+    fn iterate<N: MandelbrotNumber>(
+        &self,
+        z: &mut Complex<N>,
+        state: &mut Complex<N>,
+        c: &Complex<N>,
+    ) -> FixResult<()> {
+        z.iterate_phoenix(state, c)
+    }
+}
+
+impl Iteration for MagnetI {
+    fn iterate<N: MandelbrotNumber>(
+        &self,
+        z: &mut Complex<N>,
+        _state: &mut Complex<N>,
+        c: &Complex<N>,
+    ) -> FixResult<()> {
+        z.iterate_magnet_i(c)
+    }
+}
+
+impl Iteration for MagnetII {
+    fn iterate<N: MandelbrotNumber>(
+        &self,
+        z: &mut Complex<N>,
+        _state: &mut Complex<N>,
+        c: &Complex<N>,
+    ) -> FixResult<()> {
+        z.iterate_magnet_ii(c)
+    }
+}
+
+// How each point's value is iterated.  Every point carries one of these, so it's kept small.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Formula {
+    // z^d + c, for d >= 2.
+    Multibrot { exponent: u8 },
+    BurningShip,
+    Tricorn,
+    Perpendicular,
+    Celtic,
+    Phoenix,
+    MagnetI,
+    MagnetII,
+    // Anything else, written out by the user.
+    Expression(&'static Compiled),
+    // Newton's method for finding the roots of a polynomial, starting from each point.
+    Newton(&'static Polynomial),
+}
+
+impl Formula {
+    pub const MANDELBROT: Formula = Formula::Multibrot { exponent: 2 };
+
+    pub fn multibrot(exponent: i32) -> Result<Formula, Error> {
+        match u8::try_from(exponent) {
+            Ok(exponent) if exponent >= 2 => Ok(Formula::Multibrot { exponent }),
+            _ => bail!(
+                "Exponent {} is not supported: must be from 2 to {}",
+                exponent,
+                u8::MAX
+            ),
+        }
+    }
+
+    // Formulas are shared by every point and last as long as the program, so polynomials are
+    // leaked to keep them small.
+    pub fn newton(polynomial: Polynomial) -> Formula {
+        Formula::Newton(Box::leak(Box::new(polynomial)))
+    }
+
+    // Where the orbit of c starts, for points of the parameter plane.  The Magnet sets are drawn
+    // from the orbit of their critical point at zero.  For the rest, starting from c saves the
+    // first step from zero.
+    pub fn start<N: MandelbrotNumber>(&self, c: Complex<N>) -> Complex<N> {
+        match self {
+            Formula::MagnetI | Formula::MagnetII => Complex::zero(),
+            _ => c,
+        }
+    }
+
+    // Whether iterating relies on more than z and c.
+    pub fn uses_state(&self) -> bool {
+        matches!(self, Formula::Phoenix)
+    }
+
+    // Every point that doesn't escape lies within this distance of the origin, and any orbit that
+    // gets further away can only keep growing.  Magnet orbits can go a long way out and still come
+    // back, and Newton's method has nothing to escape from, so they have no such bound.
+    pub fn bound(&self) -> Option<f64> {
+        match *self {
+            Formula::Multibrot { exponent } => Some(2f64.powf(1.0 / (exponent - 1) as f64)),
+            Formula::MagnetI | Formula::MagnetII | Formula::Newton(_) => None,
+            _ => Some(2.0),
+        }
+    }
+
+    // The bound rounded up to a quarter, which makes a tidy radius for an initial view.  Newton's
+    // method shows a little more than the roots, and most of the Magnet sets' detail is within 2.
+    pub fn default_radius(&self) -> f64 {
+        let bound = match self {
+            Formula::Newton(p) => (p.extent() * 1.5).clamp(1.0, 3.75),
+            _ => self.bound().unwrap_or(2.0),
+        };
+        (bound * 4.0).ceil() / 4.0
+    }
+}
+
+impl Iteration for Formula {
+    #[inline(always)]
+    fn iterate<N: MandelbrotNumber>(
+        &self,
+        z: &mut Complex<N>,
+        state: &mut Complex<N>,
+        c: &Complex<N>,
+    ) -> FixResult<()> {
+        match *self {
+            Formula::Multibrot { exponent: 2 } => z.iterate_mandelbrot(c),
+            Formula::Multibrot { exponent } => z.iterate_multibrot(c, exponent as u32),
+            Formula::BurningShip => z.iterate_burning_ship(c),
+            Formula::Tricorn => z.iterate_tricorn(c),
+            Formula::Perpendicular => z.iterate_perpendicular(c),
+            Formula::Celtic => z.iterate_celtic(c),
+            Formula::Phoenix => Phoenix.iterate(z, state, c),
+            Formula::MagnetI => MagnetI.iterate(z, state, c),
+            Formula::MagnetII => MagnetII.iterate(z, state, c),
+            Formula::Expression(e) => e.iterate(z, state, c),
+            Formula::Newton(p) => p.iterate(z, state, c),
+        }
+    }
+}
+
+impl FromStr for Formula {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "mandelbrot" => Formula::MANDELBROT,
+            "burning-ship" => Formula::BurningShip,
+            "tricorn" | "mandelbar" => Formula::Tricorn,
+            "perpendicular" => Formula::Perpendicular,
+            "celtic" => Formula::Celtic,
+            "phoenix" => Formula::Phoenix,
+            "magnet1" => Formula::MagnetI,
+            "magnet2" => Formula::MagnetII,
+            _ => bail!(
+                "'{}' is not a known formula: try mandelbrot, burning-ship, tricorn, perpendicular, \
+                 celtic, phoenix, magnet1 or magnet2",
+                s
+            ),
+        })
+    }
+}
+
+impl Default for Formula {
+    fn default() -> Self {
+        Formula::MANDELBROT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::double_double::DoubleDouble;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::fix::fixn::FixN;
+    use crate::number::MandelbrotNumber;
+    use crate::point::Point;
+    use crate::set::Set;
+
+    use super::{Formula, Iteration};
+
+    fn escapes(formula: Formula, r: f64, i: f64, n: u64) -> Result<bool, Error> {
+        let c = Complex::new(Fix2x61::try_from(r)?, Fix2x61::try_from(i)?);
+        let mut p = Point::new(c);
+        p.formula = formula;
+        p.iterate_n(n)?;
+        Ok(p.escaped)
+    }
+
+    #[test]
+    fn escape_radius_shrinks() -> Result<(), Error> {
+        assert_eq!(Formula::multibrot(2)?, Formula::MANDELBROT);
+        assert_eq!(Formula::MANDELBROT.bound(), Some(2.0));
+        assert_eq!(Formula::multibrot(3)?.bound(), Some(2f64.sqrt()));
+        assert_eq!(Formula::MagnetI.bound(), None);
+        assert_eq!(Formula::multibrot(3)?.default_radius(), 1.5);
+        assert_eq!(Formula::multibrot(9)?.default_radius(), 1.25);
+        assert!(Formula::multibrot(1).is_err());
+        assert!(Formula::multibrot(-2).is_err());
+        assert!(Formula::multibrot(256).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cubic_set_on_the_real_axis() -> Result<(), Error> {
+        // z^3 + c is bounded along the real axis for |c| <= 2 / 3^1.5, about 0.385.
+        let cubic = Formula::multibrot(3)?;
+        assert!(!escapes(cubic, 0.0, 0.0, 1000)?);
+        assert!(!escapes(cubic, 0.38, 0.0, 10000)?);
+        assert!(!escapes(cubic, -0.38, 0.0, 10000)?);
+        assert!(escapes(cubic, 0.39, 0.0, 10000)?);
+        assert!(escapes(cubic, -0.39, 0.0, 10000)?);
+        // The Mandelbrot set reaches further.
+        assert!(!escapes(Formula::MANDELBROT, -0.75, 0.0, 1000)?);
+        assert!(escapes(cubic, -0.75, 0.0, 1000)?);
+        Ok(())
+    }
+
+    #[test]
+    fn higher_powers_have_rotational_symmetry() -> Result<(), Error> {
+        // The set for z^d + c is unchanged by rotating through a (d - 1)th of a turn.
+        let quartic = Formula::multibrot(4)?;
+        let turn = 2.0 * std::f64::consts::PI / 3.0;
+        for &(r, i) in [(0.6, 0.1), (-0.3, 0.5), (0.2, -0.7), (0.75, 0.0)].iter() {
+            let (rr, ri) = (
+                r * turn.cos() - i * turn.sin(),
+                r * turn.sin() + i * turn.cos(),
+            );
+            assert_eq!(
+                escapes(quartic, r, i, 50)?,
+                escapes(quartic, rr, ri, 50)?,
+                "{}, {}",
+                r,
+                i
+            );
+        }
+        Ok(())
+    }
+
+    fn step<N: MandelbrotNumber>(formula: Formula, z: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+        let mut z = Complex::new(N::from_f64(z.0).unwrap(), N::from_f64(z.1).unwrap());
+        let c = Complex::new(N::from_f64(c.0).unwrap(), N::from_f64(c.1).unwrap());
+        formula.iterate(&mut z, &mut Complex::zero(), &c).unwrap();
+        (z.r.to_f64(), z.i.to_f64())
+    }
+
+    fn variants_match_definitions<N: MandelbrotNumber>() {
+        // Few enough bits that every step is exact.
+        let c = (-0.25, 0.125);
+        for &(r, i) in [(0.5, -0.75), (-1.25, 0.25), (-0.5, -1.0), (0.75, 0.5)].iter() {
+            let square = r * r - i * i;
+            assert_eq!(
+                step::<N>(Formula::BurningShip, (r, i), c),
+                (square + c.0, 2.0 * (r * i).abs() + c.1)
+            );
+            assert_eq!(
+                step::<N>(Formula::Tricorn, (r, i), c),
+                (square + c.0, -2.0 * r * i + c.1)
+            );
+            assert_eq!(
+                step::<N>(Formula::Perpendicular, (r, i), c),
+                (square + c.0, -2.0 * r.abs() * i + c.1)
+            );
+            assert_eq!(
+                step::<N>(Formula::Celtic, (r, i), c),
+                (square.abs() + c.0, 2.0 * r * i + c.1)
+            );
+        }
+    }
+
+    #[test]
+    fn variants_in_every_number_type() {
+        variants_match_definitions::<Fix2x61>();
+        variants_match_definitions::<f64>();
+        variants_match_definitions::<DoubleDouble>();
+        variants_match_definitions::<FixN<2>>();
+    }
+
+    #[test]
+    fn phoenix_remembers_the_previous_value() {
+        // Exact in every number type.
+        let mut z = Complex::new(0.5, -0.25);
+        let mut previous = Complex::new(0.25, 0.5);
+        let c = Complex::new(-0.5, 0.5);
+        Formula::Phoenix.iterate(&mut z, &mut previous, &c).unwrap();
+        assert_eq!(z, Complex::new(-0.1875, 0.0));
+        assert_eq!(previous, Complex::new(0.5, -0.25));
+
+        // Without any weight on the previous value, it's the Mandelbrot set.
+        for &r in [-0.75, 0.3, -1.5, 0.26].iter() {
+            let c = Complex::new(Fix2x61::from_f64(r).unwrap(), Fix2x61::ZERO);
+            let (mut phoenix, mut mandelbrot) = (Point::new(c), Point::new(c));
+            phoenix.formula = Formula::Phoenix;
+            phoenix.iterate_n(100).unwrap();
+            mandelbrot.iterate_n(100).unwrap();
+            assert_eq!(phoenix.value(), mandelbrot.value(), "{}", r);
+            assert_eq!(phoenix.iterations, mandelbrot.iterations);
+        }
+    }
+
+    // Whether a point escapes or converges, and after how many iterations.
+    fn finish<N: MandelbrotNumber>(
+        formula: Formula,
+        r: f64,
+        i: f64,
+    ) -> Result<(bool, bool, u64), Error> {
+        let c = Complex::new(N::from_f64(r)?, N::from_f64(i)?);
+        let mut p = Point::with_formula(c, formula);
+        p.iterate_n(1000)?;
+        assert_eq!(p.finished(), p.escaped || p.attractor.is_some());
+        Ok((p.escaped, p.attractor == Some(0), p.iterations))
+    }
+
+    // Checked against the usual definitions in double precision, iterating from zero until
+    // |z - 1| < 1e-6 or |z| >= 100.
+    #[test]
+    fn magnets_converge_or_escape() -> Result<(), Error> {
+        let escapes = |n| (true, false, n);
+        let converges = |n| (false, true, n);
+        let neither = |f: (bool, bool, u64)| !f.0 && !f.1;
+        assert_eq!(finish::<f64>(Formula::MagnetI, 1.5, 0.0)?, converges(1));
+        assert_eq!(finish::<f64>(Formula::MagnetI, -0.5, 1.0)?, converges(9));
+        assert_eq!(finish::<f64>(Formula::MagnetI, -2.0, 0.0)?, converges(5));
+        assert_eq!(finish::<f64>(Formula::MagnetI, 0.75, -2.0625)?, escapes(11));
+        assert_eq!(finish::<f64>(Formula::MagnetI, 1.5, -2.0625)?, escapes(16));
+        // These go out past 2 and come back: 3 settles on another fixed point, at 4.
+        assert!(neither(finish::<f64>(Formula::MagnetI, 3.0, 0.0)?));
+        assert!(neither(finish::<f64>(Formula::MagnetI, 0.0, 1.0)?));
+        assert!(neither(finish::<f64>(Formula::MagnetI, 0.0, 0.25)?));
+        assert_eq!(finish::<f64>(Formula::MagnetII, -0.5, 1.0)?, converges(4));
+        assert_eq!(finish::<f64>(Formula::MagnetII, 0.0, 1.0)?, converges(7));
+        assert_eq!(
+            finish::<f64>(Formula::MagnetII, 0.375, -1.40625)?,
+            escapes(14)
+        );
+        assert_eq!(
+            finish::<f64>(Formula::MagnetII, 1.125, -1.125)?,
+            escapes(19)
+        );
+        assert!(neither(finish::<f64>(Formula::MagnetII, 0.5, 0.5)?));
+        // Fixed point can only follow orbits that stay in range.
+        assert_eq!(
+            finish::<Fix2x61>(Formula::MagnetI, -0.5, 1.0)?,
+            converges(9)
+        );
+        assert!(neither(finish::<Fix2x61>(Formula::MagnetI, 0.0, 0.25)?));
+        assert!(finish::<Fix2x61>(Formula::MagnetI, 3.0, 0.0)?.0);
+
+        // Sets start their points the same way, except for Julia sets.
+        let mut set: Set<f64> = Set::create(2, Complex::new(1.0, 0.0), 1.0)?;
+        set.set_formula(Formula::MagnetII);
+        assert!(set.points.iter().all(|p| *p.value() == Complex::zero()));
+        let mut julia = Set::create_julia(2, Complex::new(1.0, 0.0), 1.0, Complex::new(0.0, 1.0))?;
+        julia.set_formula(Formula::MagnetII);
+        assert!(julia.points.iter().all(|p| p.value() != p.loc()));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_names() -> Result<(), Error> {
+        assert_eq!(Formula::from_str("burning-ship")?, Formula::BurningShip);
+        assert_eq!(Formula::from_str("mandelbar")?, Formula::Tricorn);
+        assert!(Formula::from_str("burning ship").is_err());
+        Ok(())
+    }
+}
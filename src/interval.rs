@@ -0,0 +1,509 @@
+use std::cmp::{max, min};
+use std::collections::VecDeque;
+
+use anyhow::{bail, Context, Error};
+use rayon::prelude::*;
+
+use crate::complex::{Complex, FixResult};
+use crate::fix::fix2x29::Fix2x29;
+use crate::fix::fix2x61::Fix2x61;
+use crate::fix::fixn::FixN;
+use crate::formula::Formula;
+use crate::number::{MandelbrotNumber, WideNumber};
+use crate::set::Set;
+
+// Rigorous iteration over whole regions of the plane.  Every value is a closed box that's
+// guaranteed to contain the true orbit of every point we started with: products are exact in
+// N::Wide, and truncating back rounds lower bounds down and upper bounds up.  That's enough to
+// prove that a pixel escapes (every point in the box has |z| past the escape radius) or that it's
+// inside the set (the box eventually maps back inside an earlier one, so no orbit can ever
+// escape).
+//
+// An orbit with |z| > max(2, |c|) always escapes.  Every Mandelbrot orbit with |c| > 2 escapes
+// too, so for the Mandelbrot set the radius is just 2, but Julia sets with a large parameter need
+// a larger one.
+
+// How far back we look for a box that contains the current one.
+const MAX_PERIOD: usize = 64;
+
+// Number types with exact ordering and a smallest step, which is what outward rounding needs.
+// They also need to be Copy, which every type narrow enough to be worth classifying with is.
+pub trait IntervalNumber: MandelbrotNumber<Wide: Ord + Copy> + Ord + Copy {
+    const EPSILON: Self;
+
+    // The smallest narrow value that's no less than `wide`.  Truncation rounds down, so we only
+    // need to step up if it threw bits away.
+    fn truncate_up(wide: Self::Wide) -> FixResult<Self> {
+        let down = wide.truncate()?;
+        if down.widen() == wide {
+            Ok(down)
+        } else {
+            down.try_add(Self::EPSILON)
+        }
+    }
+}
+
+impl IntervalNumber for Fix2x61 {
+    const EPSILON: Self = Fix2x61::EPSILON;
+}
+
+impl IntervalNumber for Fix2x29 {
+    const EPSILON: Self = Fix2x29::EPSILON;
+}
+
+impl<const LIMBS: usize> IntervalNumber for FixN<LIMBS> {
+    const EPSILON: Self = FixN::epsilon();
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Interval<T> {
+    pub lo: T,
+    pub hi: T,
+}
+
+impl<T: Ord> Interval<T> {
+    pub fn new(a: T, b: T) -> Interval<T> {
+        if a <= b {
+            Interval { lo: a, hi: b }
+        } else {
+            Interval { lo: b, hi: a }
+        }
+    }
+
+    pub fn contains(&self, other: &Interval<T>) -> bool {
+        self.lo <= other.lo && other.hi <= self.hi
+    }
+}
+
+impl<T: Ord + Copy> Interval<T> {
+    pub fn intersect(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        let (lo, hi) = (max(self.lo, other.lo), min(self.hi, other.hi));
+        if lo <= hi {
+            Some(Interval { lo, hi })
+        } else {
+            None
+        }
+    }
+}
+
+impl<N: IntervalNumber> Interval<N> {
+    pub fn point(value: N) -> Interval<N> {
+        Interval {
+            lo: value,
+            hi: value,
+        }
+    }
+
+    // Rounds outwards, so the result still contains everything the wide interval did.
+    fn truncate(wide: Interval<N::Wide>) -> FixResult<Interval<N>> {
+        Ok(Interval {
+            lo: wide.lo.truncate()?,
+            hi: N::truncate_up(wide.hi)?,
+        })
+    }
+
+    // The largest absolute value of anything in the interval.
+    fn magnitude(&self) -> FixResult<N> {
+        Ok(max(N::ZERO.try_sub(self.lo)?, self.hi))
+    }
+
+    fn widen(&self) -> Interval<N::Wide> {
+        Interval {
+            lo: self.lo.widen(),
+            hi: self.hi.widen(),
+        }
+    }
+
+    // Tighter than multiplying by ourselves, as both factors are always the same value.
+    fn widening_square(&self) -> Interval<N::Wide> {
+        let (lo, hi) = (self.lo.widening_square(), self.hi.widening_square());
+        if self.lo >= N::ZERO {
+            Interval { lo, hi }
+        } else if self.hi <= N::ZERO {
+            Interval { lo: hi, hi: lo }
+        } else {
+            Interval {
+                lo: N::ZERO.widen(),
+                hi: max(lo, hi),
+            }
+        }
+    }
+
+    fn widening_mul(&self, rhs: &Interval<N>) -> Interval<N::Wide> {
+        let products = [
+            self.lo.widening_mul(rhs.lo),
+            self.lo.widening_mul(rhs.hi),
+            self.hi.widening_mul(rhs.lo),
+            self.hi.widening_mul(rhs.hi),
+        ];
+        Interval {
+            lo: *products.iter().min().unwrap(),
+            hi: *products.iter().max().unwrap(),
+        }
+    }
+}
+
+impl<W: WideNumber + Ord> Interval<W> {
+    fn try_add(self, rhs: Self) -> FixResult<Self> {
+        Ok(Interval {
+            lo: self.lo.try_add(rhs.lo)?,
+            hi: self.hi.try_add(rhs.hi)?,
+        })
+    }
+
+    fn try_sub(self, rhs: Self) -> FixResult<Self> {
+        Ok(Interval {
+            lo: self.lo.try_sub(rhs.hi)?,
+            hi: self.hi.try_sub(rhs.lo)?,
+        })
+    }
+}
+
+// A box in the complex plane.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ComplexInterval<N = Fix2x61> {
+    pub r: Interval<N>,
+    pub i: Interval<N>,
+}
+
+impl<N: IntervalNumber> ComplexInterval<N> {
+    pub const fn new(r: Interval<N>, i: Interval<N>) -> ComplexInterval<N> {
+        ComplexInterval { r, i }
+    }
+
+    pub fn point(c: &Complex<N>) -> ComplexInterval<N> {
+        ComplexInterval::new(Interval::point(c.r), Interval::point(c.i))
+    }
+
+    pub fn contains(&self, other: &ComplexInterval<N>) -> bool {
+        self.r.contains(&other.r) && self.i.contains(&other.i)
+    }
+
+    pub fn contains_point(&self, c: &Complex<N>) -> bool {
+        self.contains(&ComplexInterval::point(c))
+    }
+
+    // No less than |z| for any z in the box, as |r| + |i| never is.
+    pub fn magnitude_bound(&self) -> FixResult<N> {
+        self.r.magnitude()?.try_add(self.i.magnitude()?)
+    }
+
+    // Replaces self with a box containing z^2 + c for every z in self and c in loc.  Unlike
+    // Complex::iterate_mandelbrot there's no escape check: overflow only means the box got too
+    // big for N, which proves nothing.
+    pub fn iterate_mandelbrot(&mut self, loc: &ComplexInterval<N>) -> FixResult<()> {
+        let ComplexInterval { r, i } = self.iterate_wide(loc)?;
+        self.r = Interval::truncate(r)?;
+        self.i = Interval::truncate(i)?;
+        Ok(())
+    }
+
+    // As iterate_mandelbrot, but leaves out the part of the new box outside [-escape, escape]^2.
+    // Every orbit that gets there has escaped, so what's left contains every orbit that hasn't.
+    // Returns whether anything was left out, or None if everything was.
+    pub fn iterate_unescaped(
+        &mut self,
+        loc: &ComplexInterval<N>,
+        escape: N,
+    ) -> FixResult<Option<bool>> {
+        let ComplexInterval { r, i } = self.iterate_wide(loc)?;
+        let bounds = Interval {
+            lo: (-escape).widen(),
+            hi: escape.widen(),
+        };
+        match (r.intersect(&bounds), i.intersect(&bounds)) {
+            (Some(r_), Some(i_)) => {
+                self.r = Interval::truncate(r_)?;
+                self.i = Interval::truncate(i_)?;
+                Ok(Some(r_ != r || i_ != i))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn iterate_wide(&self, loc: &ComplexInterval<N>) -> FixResult<ComplexInterval<N::Wide>> {
+        let ri = self.r.widening_mul(&self.i);
+        let r = self
+            .r
+            .widening_square()
+            .try_sub(self.i.widening_square())?
+            .try_add(loc.r.widen())?;
+        let i = ri.try_add(ri)?.try_add(loc.i.widen())?;
+        Ok(ComplexInterval { r, i })
+    }
+
+    // True if every point in the box is further than `escape` from the origin.
+    pub fn escaped(&self, escape: N) -> bool {
+        let closest = self
+            .r
+            .widening_square()
+            .lo
+            .try_add(self.i.widening_square().lo);
+        // Both squares are positive, so an overflow is certainly further than anything in N.
+        closest.map_or(true, |n| n > escape.widening_square())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Classification {
+    // Every point in the pixel escapes.
+    Escaping,
+    // Every point in the pixel is in the set.
+    Interior,
+    // We couldn't prove either within the iteration limit.
+    Undetermined,
+}
+
+// Classifies the points whose orbits start in `start` and add `c` on each iteration.  For the
+// Mandelbrot set the two are the same box; for a Julia set `c` is a single point.  `escape` is the
+// escape radius for those orbits.
+pub fn classify<N: IntervalNumber>(
+    start: &ComplexInterval<N>,
+    c: &ComplexInterval<N>,
+    escape: N,
+    max_iter: u64,
+) -> Classification {
+    let bounds = Interval {
+        lo: -escape,
+        hi: escape,
+    };
+    let mut z = match (start.r.intersect(&bounds), start.i.intersect(&bounds)) {
+        (Some(r), Some(i)) => ComplexInterval::new(r, i),
+        _ => return Classification::Escaping,
+    };
+    // Once any orbit has escaped, the boxes only describe the rest, which can't prove anything
+    // about the whole pixel being inside.
+    let mut some_escaped = z != *start;
+    let mut earlier: VecDeque<ComplexInterval<N>> = VecDeque::with_capacity(MAX_PERIOD);
+    for _ in 0..max_iter {
+        if z.escaped(escape) {
+            return Classification::Escaping;
+        }
+        // If some later box fits inside an earlier one, iterating it can only ever repeat the
+        // boxes in between, so every orbit stays bounded.
+        if !some_escaped {
+            if earlier.iter().any(|e| e.contains(&z)) {
+                return Classification::Interior;
+            }
+            if earlier.len() == MAX_PERIOD {
+                earlier.pop_front();
+            }
+            earlier.push_back(z);
+        }
+        match z.iterate_unescaped(c, escape) {
+            Ok(Some(dropped)) => some_escaped |= dropped,
+            Ok(None) => return Classification::Escaping,
+            Err(_) => return Classification::Undetermined,
+        }
+    }
+    Classification::Undetermined
+}
+
+impl<N: IntervalNumber> Set<N> {
+    // The square around each of our points, indexed the same way.  Edges are shared between
+    // neighbouring pixels, so together they cover the whole view.
+    pub fn pixel_boxes(&self) -> Result<Vec<ComplexInterval<N>>, Error> {
+        let size = self.size() as usize;
+        let step = self
+            .radius
+            .widening_mul(N::power_of_two(1 - self.power_size as i32)?)
+            .truncate()?;
+        let edges = |start: N| -> Result<Vec<Interval<N>>, Error> {
+            let mut edges = Vec::with_capacity(size);
+            let mut lo = start;
+            for _ in 0..size {
+                let hi = lo.try_add(step)?;
+                edges.push(Interval { lo, hi });
+                lo = hi;
+            }
+            Ok(edges)
+        };
+        let rs = edges(self.centre.r.try_sub(self.radius)?)?;
+        let is = edges(self.centre.i.try_sub(self.radius)?)?;
+        Ok(is
+            .iter()
+            .flat_map(|&i| rs.iter().map(move |&r| ComplexInterval::new(r, i)))
+            .collect())
+    }
+
+    pub fn classify(&self, max_iter: u64) -> Result<Vec<Classification>, Error> {
+        if self.formula != Formula::MANDELBROT {
+            bail!("Interval classification only supports z^2 + c");
+        }
+        let julia = self.julia.as_ref().map(ComplexInterval::point);
+        let escape = match &julia {
+            Some(c) => max(
+                N::TWO,
+                c.magnitude_bound()
+                    .context("Julia parameter too large to classify")?,
+            ),
+            None => N::TWO,
+        };
+        Ok(self
+            .pixel_boxes()?
+            .par_iter()
+            .map(|b| classify(b, julia.as_ref().unwrap_or(b), escape, max_iter))
+            .collect())
+    }
+}
+
+impl<N: MandelbrotNumber> Set<N> {
+    // Lower and upper bounds on the area of the set within this view, from its classification.
+    pub fn area_bounds(&self, classes: &[Classification]) -> (f64, f64) {
+        let side = 2.0 * self.radius.to_f64() / self.size() as f64;
+        let count = |class| classes.iter().filter(|&&c| c == class).count() as f64;
+        let interior = count(Classification::Interior);
+        let undetermined = count(Classification::Undetermined);
+        (
+            interior * side * side,
+            (interior + undetermined) * side * side,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::complex::{Complex, FixResult};
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::number::MandelbrotNumber;
+    use crate::point::Point;
+    use crate::set::Set;
+
+    use super::{classify, Classification, ComplexInterval, Interval, IntervalNumber};
+
+    fn square(r: f64, i: f64, half: f64) -> FixResult<ComplexInterval> {
+        let interval = |x: f64| -> FixResult<Interval<Fix2x61>> {
+            Ok(Interval::new(
+                Fix2x61::try_from(x - half)?,
+                Fix2x61::try_from(x + half)?,
+            ))
+        };
+        Ok(ComplexInterval::new(interval(r)?, interval(i)?))
+    }
+
+    fn mandelbrot(c: &ComplexInterval, max_iter: u64) -> Classification {
+        classify(c, c, Fix2x61::two(), max_iter)
+    }
+
+    #[test]
+    fn rounds_outwards() -> FixResult<()> {
+        let third = Fix2x61::try_from(1.0 / 3.0)?;
+        let product = third.widening_square();
+        let up = Fix2x61::truncate_up(product)?;
+        assert_eq!(up, product.truncate()?.try_add(Fix2x61::EPSILON)?);
+        assert!(up.widening_mul(Fix2x61::one()) > product);
+        assert_eq!(
+            Fix2x61::truncate_up(Fix2x61::one().widen())?,
+            Fix2x61::one()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn boxes_contain_orbits() -> FixResult<()> {
+        let c = Complex::new(Fix2x61::try_from(-0.7)?, Fix2x61::try_from(0.25)?);
+        let loc = ComplexInterval::point(&c);
+        let (mut z, mut zs) = (c, loc);
+        for _ in 0..20 {
+            z.iterate_mandelbrot(&c)?;
+            zs.iterate_mandelbrot(&loc)?;
+            assert!(zs.contains_point(&z), "{:?} outside {:?}", z, zs);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn classifies_known_regions() -> FixResult<()> {
+        assert_eq!(
+            mandelbrot(&square(1.0, 1.0, 0.01)?, 100),
+            Classification::Escaping
+        );
+        assert_eq!(
+            mandelbrot(&square(-0.1, 0.05, 0.01)?, 1000),
+            Classification::Interior
+        );
+        // The period two bulb.
+        assert_eq!(
+            mandelbrot(&square(-1.0, 0.0, 0.01)?, 1000),
+            Classification::Interior
+        );
+        // Straddles the boundary at the cusp.
+        assert_eq!(
+            mandelbrot(&square(0.25, 0.0, 0.01)?, 1000),
+            Classification::Undetermined
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn agrees_with_escape_time() -> Result<(), Error> {
+        let set: Set = Set::create(
+            5,
+            Complex::new(Fix2x61::try_from(-0.5)?, Fix2x61::zero()),
+            Fix2x61::two(),
+        )?;
+        let boxes = set.pixel_boxes()?;
+        let classes = set.classify(1000)?;
+        for (b, class) in boxes.iter().zip(classes.iter()) {
+            let samples = [
+                Complex::new(b.r.lo, b.i.lo),
+                Complex::new(b.r.hi, b.i.hi),
+                Complex::new(
+                    Fix2x61::try_from((b.r.lo.to_f64() + b.r.hi.to_f64()) / 2.0)?,
+                    Fix2x61::try_from((b.i.lo.to_f64() + b.i.hi.to_f64()) / 2.0)?,
+                ),
+            ];
+            for c in samples.iter() {
+                let mut point = Point::new(*c);
+                point.iterate_n(5000)?;
+                match class {
+                    Classification::Escaping => assert!(point.escaped, "{:?}", b),
+                    Classification::Interior => assert!(!point.escaped, "{:?}", b),
+                    Classification::Undetermined => {}
+                }
+            }
+        }
+        assert!(classes.contains(&Classification::Escaping));
+        assert!(classes.contains(&Classification::Interior));
+        Ok(())
+    }
+
+    #[test]
+    fn classifies_julia_sets_with_large_parameters() -> Result<(), Error> {
+        // The repelling fixed point (1 + sqrt(15)) / 2 is in the Julia set for -3.5, and outside
+        // the radius the Mandelbrot set escapes at.
+        let c = Complex::new(Fix2x61::try_from(-3.5)?, Fix2x61::zero());
+        let fixed = (1.0 + 15f64.sqrt()) / 2.0;
+        let set: Set = Set::create_julia(
+            2,
+            Complex::new(Fix2x61::try_from(fixed)?, Fix2x61::zero()),
+            Fix2x61::try_from(0.001)?,
+            c,
+        )?;
+        let boxes = set.pixel_boxes()?;
+        let classes = set.classify(1000)?;
+        let fixed = Complex::new(Fix2x61::try_from(fixed)?, Fix2x61::zero());
+        let (b, class) = boxes
+            .iter()
+            .zip(classes.iter())
+            .find(|(b, _)| b.contains_point(&fixed))
+            .unwrap();
+        assert_ne!(*class, Classification::Escaping, "{:?}", b);
+        Ok(())
+    }
+
+    #[test]
+    fn bounds_the_area() -> Result<(), Error> {
+        let centre = Complex::new(Fix2x61::try_from(-0.5)?, Fix2x61::zero());
+        let set: Set = Set::create(6, centre, Fix2x61::two())?;
+        let (lower, upper) = set.area_bounds(&set.classify(1000)?);
+        // The best known estimate of the area is about 1.5066.
+        assert!(lower > 0.25 && lower < 1.5066, "{}", lower);
+        assert!(upper > 1.5066 && upper < 5.0, "{}", upper);
+        Ok(())
+    }
+}
@@ -1,11 +1,22 @@
 extern crate image;
 extern crate num;
 
+pub mod buddhabrot;
 pub mod colours;
 pub mod complex;
+pub mod double_double;
+pub mod expression;
 pub mod extending;
 pub mod fix;
+pub mod formula;
+pub mod interval;
+pub mod mariani_silver;
+pub mod newton;
+pub mod number;
+pub mod perturbation;
 pub mod point;
+pub mod promoting;
+pub mod series;
 pub mod set;
 pub mod zoom_path;
 
@@ -6,18 +6,24 @@ extern crate num;
 
 use std::convert::TryFrom;
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use structopt::StructOpt;
 
+use mandelbrot::buddhabrot::Buddhabrot;
 use mandelbrot::colours::ColourScheme;
 use mandelbrot::complex::Complex;
+use mandelbrot::expression::Expression;
 use mandelbrot::fix::fix2x61::Fix2x61;
-use mandelbrot::set::Set;
+use mandelbrot::formula::Formula;
+use mandelbrot::newton::Polynomial;
+use mandelbrot::promoting::PromotingSet;
+use mandelbrot::set::{Orientation, Seeding, Strategy};
 use mandelbrot::zoom_path::ZoomPath;
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "mandelbrot")]
 struct Args {
+    /// Quadrants to zoom into, from 1 to 4 separated by commas.
     #[structopt(long)]
     path: Option<ZoomPath>,
     #[structopt(short, long)]
@@ -26,23 +32,91 @@ struct Args {
     verbose: bool,
     #[structopt(default_value = "fractint", short, long)]
     scheme: ColourScheme,
+    /// How to pick which points to iterate: flood, which spreads out from the edge of the picture
+    /// through escaping points, or mariani-silver, which fills in rectangles whose borders are all
+    /// the same.
+    #[structopt(default_value = "flood", long)]
+    strategy: Strategy,
+    /// Where flooding starts from besides the edge of the picture, to find escaping points cut off
+    /// from it: border for nowhere else, grid, random (or random:<seed>) or full.
+    #[structopt(default_value = "border", long)]
+    seeding: Seeding,
+    /// Iterate against a high precision reference orbit, rather than every point at full
+    /// precision.  Much faster for deep zooms.
+    #[structopt(long)]
+    perturbation: bool,
+    /// How far to iterate each point when using perturbation.
+    #[structopt(default_value = "10000", long)]
+    max_iterations: u64,
+    /// Skip early iterations using a series approximation, accurate to this relative tolerance
+    /// (for example 1e-9).
+    #[structopt(long)]
+    series_tolerance: Option<f64>,
+    /// Render the Julia set for this parameter (for example -0.8,0.156) rather than the
+    /// Mandelbrot set.
+    #[structopt(long, allow_hyphen_values = true)]
+    julia: Option<Complex>,
+    /// Iterate z^exponent + c.
+    #[structopt(default_value = "2", long, allow_hyphen_values = true)]
+    exponent: i32,
+    /// Which fractal to render: mandelbrot, burning-ship, tricorn, perpendicular, celtic, phoenix,
+    /// magnet1 or magnet2.
+    #[structopt(default_value = "mandelbrot", long)]
+    fractal: Formula,
+    /// Iterate a formula of your own in z and c, for example "z^3 + c*z + c".  It may use + - * /,
+    /// whole number powers, brackets, i and conj().
+    #[structopt(long)]
+    formula: Option<Expression>,
+    /// Find the roots of a polynomial with Newton's method, given its roots as a list like
+    /// "1 -0.5,0.866 -0.5,-0.866".  The roots colour scheme shows which root each point finds.
+    #[structopt(long, allow_hyphen_values = true, parse(try_from_str = Polynomial::parse_roots))]
+    newton_roots: Option<Polynomial>,
+    /// The same, given the polynomial's coefficients from the highest power down, like "1 0 0 -1"
+    /// for z^3 - 1.
+    #[structopt(
+        long,
+        allow_hyphen_values = true,
+        parse(try_from_str = Polynomial::parse_coefficients)
+    )]
+    newton_coefficients: Option<Polynomial>,
+    /// Render a Buddhabrot from this many samples of c: the density of escaping orbits rather than
+    /// how long each point takes to escape.
+    #[structopt(long)]
+    buddhabrot: Option<u64>,
+    /// Iteration limits for the Buddhabrot, one for greyscale or three for a red, green and blue
+    /// Nebulabrot like 5000,500,50.  Defaults to --max-iterations.
+    #[structopt(long, use_delimiter = true)]
+    limits: Vec<u64>,
+    /// Draw the imaginary axis pointing up, as in the usual mathematical convention.  Zoom paths
+    /// follow the picture either way.
+    #[structopt(long)]
+    imaginary_up: bool,
 }
 
 fn main() -> Result<(), Error> {
     let args = Args::from_args();
 
-    let centre = Complex::zero();
-    let radius: Fix2x61 = Fix2x61::try_from(2i8)?;
-
-    let mut set: Set = Set::create(8, centre, radius).context("Creating the set")?;
-    set.iterate_as_required(500, args.verbose)?;
-
-    if let Some(path) = args.path {
-        for quad in path.0.iter() {
-            set = set.subset(quad)?;
-            set.iterate_as_required(set.seen_escapes_to(), args.verbose)?;
+    let newton = match (args.newton_roots, args.newton_coefficients) {
+        (Some(_), Some(_)) => bail!("Give either the roots or the coefficients, not both"),
+        (roots, coefficients) => roots.or(coefficients),
+    };
+    let default = args.fractal == Formula::MANDELBROT && args.exponent == 2;
+    let formula = match (newton, args.formula, args.fractal) {
+        (Some(_), Some(_), _) => bail!("Newton's method can't be combined with a formula"),
+        (Some(p), None, _) if default => Formula::newton(p),
+        (Some(_), None, _) => {
+            bail!("Newton's method can't be combined with --fractal or --exponent")
         }
-    }
+        (None, Some(expression), _) if default => expression.compile()?,
+        (None, Some(_), _) => bail!("A formula can't be combined with --fractal or --exponent"),
+        (None, None, Formula::MANDELBROT) => Formula::multibrot(args.exponent)?,
+        (None, None, _) if args.exponent != 2 => {
+            bail!("Only the Mandelbrot set supports other exponents")
+        }
+        (None, None, fractal) => fractal,
+    };
+    let centre = Complex::zero();
+    let radius = Fix2x61::try_from(formula.default_radius())?;
 
     let filename = if let Some(name) = args.file {
         name
@@ -57,6 +131,70 @@ fn main() -> Result<(), Error> {
         format!("{}.png", filename)
     };
 
+    if let Some(samples) = args.buddhabrot {
+        if formula != Formula::MANDELBROT || args.julia.is_some() {
+            bail!("The Buddhabrot is only drawn for the Mandelbrot set")
+        }
+        let limits = match args.limits {
+            limits if limits.is_empty() => vec![args.max_iterations],
+            limits => limits,
+        };
+        let mut buddhabrot = Buddhabrot::create(8, centre, radius, limits)?;
+        if args.imaginary_up {
+            buddhabrot.set_orientation(Orientation::ImaginaryUp);
+        }
+        if let Some(path) = args.path {
+            for quad in path.0.iter() {
+                buddhabrot = buddhabrot.subset(quad)?;
+            }
+        }
+        let escaped = buddhabrot.sample(samples)?;
+        if args.verbose {
+            println!("{} of {} samples escaped", escaped, samples);
+        }
+        buddhabrot.render_to_file(&filename)?;
+        return Ok(());
+    }
+
+    let mut set = match args.julia {
+        Some(c) => PromotingSet::create_julia(8, centre, radius, c),
+        None => PromotingSet::create(8, centre, radius),
+    }
+    .context("Creating the set")?;
+    set.set_formula(formula);
+    if args.imaginary_up {
+        set.set_orientation(Orientation::ImaginaryUp);
+    }
+    set.set_series_tolerance(args.series_tolerance);
+    set.set_strategy(args.strategy);
+    set.set_seeding(args.seeding);
+    if matches!(args.scheme, ColourScheme::Distance) {
+        if args.perturbation || args.series_tolerance.is_some() {
+            bail!("The distance colour scheme needs every iteration, so can't skip any")
+        }
+        set.estimate_distances();
+    }
+    if !args.perturbation {
+        set.iterate_as_required(500, args.verbose)?;
+    }
+
+    if let Some(path) = args.path {
+        for quad in path.0.iter() {
+            let number_type = set.number_type();
+            set = set.subset(quad)?;
+            if args.verbose && set.number_type() != number_type {
+                println!("Switched to {}", set.number_type());
+            }
+            if !args.perturbation {
+                set.iterate_as_required(set.seen_escapes_to(), args.verbose)?;
+            }
+        }
+    }
+
+    if args.perturbation {
+        set.iterate_perturbed(args.max_iterations, args.verbose)?;
+    }
+
     set.render_to_file(&args.scheme, &filename)?;
 
     Ok(())
@@ -0,0 +1,236 @@
+use anyhow::{Context, Error};
+use rayon::prelude::*;
+
+use crate::number::MandelbrotNumber;
+use crate::point::Point;
+use crate::set::Set;
+
+// A rectangle of the picture, including its border: the columns from left to right and the rows
+// from top to bottom.
+#[derive(Copy, Clone, Debug)]
+struct Rectangle {
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+}
+
+impl Rectangle {
+    fn border(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let rows = (self.left..=self.right).flat_map(move |x| [(x, self.top), (x, self.bottom)]);
+        let columns =
+            (self.top + 1..self.bottom).flat_map(move |y| [(self.left, y), (self.right, y)]);
+        rows.chain(columns)
+    }
+
+    fn inside(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.top + 1..self.bottom)
+            .flat_map(move |y| (self.left + 1..self.right).map(move |x| (x, y)))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.right - self.left < 2 || self.bottom - self.top < 2
+    }
+
+    // Four quarters, which share the lines through the middle.
+    fn split(&self) -> [Rectangle; 4] {
+        let (x, y) = ((self.left + self.right) / 2, (self.top + self.bottom) / 2);
+        [
+            Rectangle {
+                right: x,
+                bottom: y,
+                ..*self
+            },
+            Rectangle {
+                left: x,
+                bottom: y,
+                ..*self
+            },
+            Rectangle {
+                right: x,
+                top: y,
+                ..*self
+            },
+            Rectangle {
+                left: x,
+                top: y,
+                ..*self
+            },
+        ]
+    }
+}
+
+// What iterating a point found out, which is all that colouring it needs.  Points that haven't
+// escaped or converged by the limit all look the same, however far they got.
+fn outcome<N: MandelbrotNumber>(p: &Point<N>) -> Option<(Option<u8>, u64)> {
+    if p.finished() {
+        Some((p.attractor, p.iterations))
+    } else {
+        None
+    }
+}
+
+impl<N: MandelbrotNumber> Set<N> {
+    // Mariani-Silver subdivision.  Rectangles are handled a generation at a time, so that the
+    // borders of all of them can be iterated together.
+    pub(crate) fn iterate_subdividing(
+        &mut self,
+        max_iter: u64,
+        verbose: bool,
+    ) -> Result<(), Error> {
+        let size = 1 << self.power_size;
+        let index = |(x, y): (usize, usize)| x + size * y;
+        let mut rectangles = vec![Rectangle {
+            left: 0,
+            top: 0,
+            right: size - 1,
+            bottom: size - 1,
+        }];
+        // Borders are shared, so we keep track of which points we've already iterated.
+        let (mut wanted, mut done) = (vec![false; size * size], vec![false; size * size]);
+        let mut filled = 0;
+
+        while !rectangles.is_empty() {
+            for p in rectangles.iter().flat_map(Rectangle::border) {
+                wanted[index(p)] = !done[index(p)];
+            }
+            self.points
+                .par_iter_mut()
+                .zip(wanted.par_iter())
+                .filter(|(_, &wanted)| wanted)
+                .try_for_each(|(p, _)| {
+                    p.escape_candidate = true;
+                    p.iterate_to_n(max_iter)
+                        .with_context(|| format!("Iterating point {:?}", p.value()))
+                })?;
+            for (wanted, done) in wanted.iter_mut().zip(done.iter_mut()) {
+                *done |= *wanted;
+                *wanted = false;
+            }
+
+            let mut next = vec![];
+            for rectangle in rectangles {
+                // Rectangles too thin to split are all border, which we've just iterated.
+                if rectangle.is_empty() {
+                    continue;
+                }
+                let first = outcome(&self.points[index((rectangle.left, rectangle.top))]);
+                // Escaped points each have a distance of their own, so they can't be filled in.
+                let fillable = !(self.distances && matches!(first, Some((None, _))));
+                if fillable
+                    && rectangle
+                        .border()
+                        .all(|p| outcome(&self.points[index(p)]) == first)
+                {
+                    // Anything inside the set is already as it should be.
+                    if let Some((attractor, iterations)) = first {
+                        for p in rectangle.inside() {
+                            let p = &mut self.points[index(p)];
+                            p.escaped = attractor.is_none();
+                            p.attractor = attractor;
+                            p.iterations = iterations;
+                        }
+                    }
+                    filled += rectangle.inside().count();
+                } else {
+                    next.extend(rectangle.split());
+                }
+            }
+            rectangles = next;
+        }
+
+        if verbose {
+            println!(
+                "Mariani-Silver iterated {} points and filled in {} of {}",
+                done.iter().filter(|&&done| done).count(),
+                filled,
+                size * size
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::formula::Formula;
+    use crate::set::{Set, Strategy};
+
+    use super::outcome;
+
+    // The same view rendered both ways, with every point of the brute force one iterated.
+    fn both_ways(
+        centre: (f64, f64),
+        radius: Fix2x61,
+        formula: Formula,
+    ) -> Result<(Set, Set), Error> {
+        let centre = Complex::new(Fix2x61::try_from(centre.0)?, Fix2x61::try_from(centre.1)?);
+        let mut subdivided: Set = Set::create(6, centre, radius)?;
+        subdivided.set_formula(formula);
+        subdivided.set_strategy(Strategy::MarianiSilver);
+        subdivided.iterate_as_required(100, false)?;
+
+        let mut brute_force: Set = Set::create(6, centre, radius)?;
+        brute_force.set_formula(formula);
+        brute_force
+            .points
+            .iter_mut()
+            .for_each(|p| p.escape_candidate = true);
+        brute_force.iterate_to(200);
+        Ok((subdivided, brute_force))
+    }
+
+    fn differences(a: &Set, b: &Set) -> usize {
+        a.points
+            .iter()
+            .zip(b.points.iter())
+            .filter(|(a, b)| outcome(a) != outcome(b))
+            .count()
+    }
+
+    // The Mandelbrot set is connected, so a border all the same can't have anything different
+    // inside it unless it's too small for the points to see.
+    #[test]
+    fn matches_brute_force() -> Result<(), Error> {
+        for &(centre, radius) in &[
+            ((0.0, 0.0), Fix2x61::two()),
+            ((-0.75, 0.1), Fix2x61::power_of_two(-4)?),
+        ] {
+            let (subdivided, brute_force) = both_ways(centre, radius, Formula::MANDELBROT)?;
+            assert_eq!(differences(&subdivided, &brute_force), 0, "{:?}", centre);
+            let iterated = subdivided.points.iter().filter(|p| p.escape_candidate);
+            assert!(iterated.count() < 64 * 64 * 3 / 4, "{:?}", centre);
+        }
+        Ok(())
+    }
+
+    // Every escaped point needs iterating to get its own distance, but interior points can still
+    // be filled in.
+    #[test]
+    fn estimates_every_distance() -> Result<(), Error> {
+        let mut set: Set = Set::create(6, Complex::zero(), Fix2x61::two())?;
+        set.set_strategy(Strategy::MarianiSilver);
+        set.estimate_distances();
+        set.iterate_as_required(100, false)?;
+        let escaped = set.points.iter().filter(|p| p.escaped);
+        assert!(escaped.clone().count() > 0);
+        assert!(escaped.clone().all(|p| p.distance().is_some()));
+        assert!(set.points.iter().any(|p| !p.escape_candidate));
+        Ok(())
+    }
+
+    // Burning Ship isn't, but the picture should be nearly right all the same.
+    #[test]
+    fn nearly_matches_disconnected_fractals() -> Result<(), Error> {
+        let radius = Fix2x61::power_of_two(-1)?;
+        let (subdivided, brute_force) = both_ways((-0.5, -0.5), radius, Formula::BurningShip)?;
+        assert!(differences(&subdivided, &brute_force) < 64 * 64 / 100);
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error};
+
+use crate::complex::{overflow_escapes, Complex, FixError, FixResult};
+use crate::formula::Iteration;
+use crate::number::MandelbrotNumber;
+
+// A polynomial for Newton's method, which we keep as its roots: those are what points converge
+// on, and their order is the order of the attractors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polynomial {
+    roots: Vec<Complex<f64>>,
+}
+
+// Roots are always finite, so comparisons are too.
+impl Eq for Polynomial {}
+
+impl Polynomial {
+    pub fn from_roots(roots: Vec<Complex<f64>>) -> Result<Polynomial, Error> {
+        if roots.is_empty() || roots.len() > u8::MAX as usize {
+            bail!("A polynomial needs from 1 to {} roots", u8::MAX)
+        }
+        for root in roots.iter() {
+            if !(root.r.abs() < 4.0 && root.i.abs() < 4.0) {
+                bail!(
+                    "root {} is out of range: its parts must be less than 4 in magnitude",
+                    root
+                )
+            }
+        }
+        Ok(Polynomial { roots })
+    }
+
+    // Coefficients run from the highest power down to the constant term.  We find the roots with
+    // the Durand-Kerner method, which improves guesses at all of them at once.
+    pub fn from_coefficients(coefficients: Vec<Complex<f64>>) -> Result<Polynomial, Error> {
+        let coefficients = match coefficients
+            .iter()
+            .position(|&a| a != Complex::new(0.0, 0.0))
+        {
+            Some(first) => &coefficients[first..],
+            None => bail!("A polynomial needs a coefficient that isn't zero"),
+        };
+        let degree = coefficients.len() - 1;
+        if degree == 0 {
+            bail!("A polynomial needs at least one root, so some power of z")
+        }
+        let lead = coefficients[0];
+        let monic: Vec<_> = coefficients.iter().map(|&a| a.divided_by(lead)).collect();
+        let evaluate = |z| {
+            monic
+                .iter()
+                .fold(Complex::new(0.0, 0.0), |p, &a| p.times(z).plus(a))
+        };
+
+        let seed = Complex::new(0.4, 0.9);
+        let mut roots: Vec<_> = (0..degree)
+            .scan(Complex::new(1.0, 0.0), |power, _| {
+                let root = *power;
+                *power = power.times(seed);
+                Some(root)
+            })
+            .collect();
+        for _ in 0..1000 {
+            let mut largest_change: f64 = 0.0;
+            for j in 0..degree {
+                let others = (0..degree)
+                    .filter(|&k| k != j)
+                    .fold(Complex::new(1.0, 0.0), |p, k| {
+                        p.times(roots[j].minus(roots[k]))
+                    });
+                let change = evaluate(roots[j]).divided_by(others);
+                roots[j] = roots[j].minus(change);
+                largest_change = largest_change.max(change.magnitude_sqr());
+            }
+            if largest_change < 1e-30 {
+                break;
+            }
+        }
+        if roots.iter().any(|r| !r.r.is_finite() || !r.i.is_finite()) {
+            bail!("Couldn't find the roots of the polynomial")
+        }
+        Polynomial::from_roots(roots)
+    }
+
+    // Roots or coefficients for the command line, as a list of numbers like 0.5 or -0.5,0.866
+    // separated by spaces.
+    pub fn parse_roots(s: &str) -> Result<Polynomial, Error> {
+        Polynomial::from_roots(parse_list(s)?)
+    }
+
+    pub fn parse_coefficients(s: &str) -> Result<Polynomial, Error> {
+        Polynomial::from_coefficients(parse_list(s)?)
+    }
+
+    pub fn roots(&self) -> &[Complex<f64>] {
+        &self.roots
+    }
+
+    // How far out the roots go.
+    pub fn extent(&self) -> f64 {
+        self.roots
+            .iter()
+            .map(|r| r.magnitude_sqr().sqrt())
+            .fold(0.0, f64::max)
+    }
+
+    // The attractor a value has got close enough to, if any.
+    fn attractor<N: MandelbrotNumber>(&self, z: &Complex<N>) -> Option<u8> {
+        let z = Complex::new(z.r.to_f64(), z.i.to_f64());
+        self.roots
+            .iter()
+            .position(|&r| z.minus(r).magnitude_sqr() < 1e-12)
+            .map(|k| k as u8)
+    }
+}
+
+// z = z - p(z) / p'(z)
+//
+// With p(z) the product of every (z - r), p'(z) / p(z) is the sum of every 1 / (z - r).  Even
+// with values inside our range, p(z) can easily be far outside it, so we work with the factors
+// scaled down by eight, keeping them all smaller than one.  Orbits that leave the range are
+// treated as escaping, the same as if they'd hit a critical point and been thrown out to
+// infinity.
+impl Iteration for Polynomial {
+    fn iterate<N: MandelbrotNumber>(
+        &self,
+        z: &mut Complex<N>,
+        _state: &mut Complex<N>,
+        _c: &Complex<N>,
+    ) -> FixResult<()> {
+        let step = || {
+            let eighth = N::ONE.halve()?.halve()?.halve()?;
+            let scaled = (z.clone() * eighth.clone())?;
+            // Products and their derivatives together, one factor at a time.
+            let (mut p, mut dp) = (Complex::one(), Complex::zero());
+            for root in self.roots.iter() {
+                let root = Complex::new(N::from_f64(root.r)?, N::from_f64(root.i)?);
+                let factor = (scaled.clone() - (root * eighth.clone())?)?;
+                dp = ((dp * factor.clone())? + p.clone())?;
+                p = (p * factor)?;
+            }
+            let mut step = (p / dp)?;
+            for _ in 0..3 {
+                step = (step.clone() + step)?;
+            }
+            z.clone() - step
+        };
+        *z = step().map_err(overflow_escapes)?;
+        match self.attractor(z) {
+            Some(attractor) => Err(FixError::Converged { attractor }),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_list(s: &str) -> Result<Vec<Complex<f64>>, Error> {
+    s.split_whitespace()
+        .enumerate()
+        .map(|(i, arg)| -> Result<Complex<f64>, Error> {
+            let parts: Result<Vec<_>, _> = arg.split(',').map(f64::from_str).collect();
+            let parts = parts.with_context(|| {
+                format!(
+                    "element {} is {} but should be a number like 0.5 or -0.5,0.866",
+                    i, arg
+                )
+            })?;
+            Ok(match parts[..] {
+                [r] => Complex::new(r, 0.0),
+                [r, i] => Complex::new(r, i),
+                _ => bail!(
+                    "element {} has {} parts but should have 1 or 2",
+                    i,
+                    parts.len()
+                ),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::formula::Formula;
+    use crate::point::Point;
+
+    use super::Polynomial;
+
+    fn has_root(polynomial: &Polynomial, r: f64, i: f64) -> bool {
+        let expected = Complex::new(r, i);
+        polynomial
+            .roots()
+            .iter()
+            .any(|r| r.minus(expected).magnitude_sqr() < 1e-20)
+    }
+
+    #[test]
+    fn finds_roots_from_coefficients() -> Result<(), Error> {
+        // z^3 - 1
+        let cube_roots = Polynomial::parse_coefficients("2 0 0 -2")?;
+        assert_eq!(cube_roots.roots().len(), 3);
+        let third = 2.0 * std::f64::consts::PI / 3.0;
+        for k in 0..3 {
+            let angle = third * k as f64;
+            assert!(has_root(&cube_roots, angle.cos(), angle.sin()), "{}", k);
+        }
+        // (z - i)(z + 0.5)
+        let quadratic = Polynomial::parse_coefficients("0 1 0.5,-1 0,-0.5")?;
+        assert_eq!(quadratic.roots().len(), 2);
+        assert!(has_root(&quadratic, 0.0, 1.0));
+        assert!(has_root(&quadratic, -0.5, 0.0));
+        Ok(())
+    }
+
+    #[test]
+    fn reports_bad_lists() {
+        let error = |s| Polynomial::parse_roots(s).unwrap_err().to_string();
+        assert_eq!(
+            error("1 x,0"),
+            "element 1 is x,0 but should be a number like 0.5 or -0.5,0.866"
+        );
+        assert_eq!(
+            error("1 0,1,2"),
+            "element 1 has 3 parts but should have 1 or 2"
+        );
+        assert!(Polynomial::parse_roots("").is_err());
+        assert!(Polynomial::parse_roots("4,0").is_err());
+        assert!(Polynomial::parse_coefficients("0 0 3").is_err());
+    }
+
+    fn newton(formula: Formula, r: f64, i: f64) -> Result<Point, Error> {
+        let z = Complex::new(Fix2x61::try_from(r)?, Fix2x61::try_from(i)?);
+        let mut p = Point::new(z);
+        p.formula = formula;
+        p.iterate_n(100)?;
+        Ok(p)
+    }
+
+    #[test]
+    fn points_find_their_roots() -> Result<(), Error> {
+        let formula = Formula::newton(Polynomial::parse_roots("1 -0.5,0.866 -0.5,-0.866")?);
+        assert_eq!(newton(formula, 2.0, 0.0)?.attractor, Some(0));
+        assert_eq!(newton(formula, -1.0, 1.5)?.attractor, Some(1));
+        assert_eq!(newton(formula, -1.0, -1.5)?.attractor, Some(2));
+        let close = newton(formula, 1.0, 0.001)?;
+        let far = newton(formula, 3.0, 0.001)?;
+        assert_eq!((close.attractor, far.attractor), (Some(0), Some(0)));
+        assert!(close.iterations < far.iterations);
+        // The derivative is zero at the origin, which throws it out of range.
+        let critical = newton(formula, 0.0, 0.0)?;
+        assert!(critical.escaped && critical.attractor.is_none());
+        assert_eq!(formula.default_radius(), 1.5);
+        Ok(())
+    }
+}
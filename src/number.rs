@@ -0,0 +1,621 @@
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::ops::Neg;
+
+use crate::complex::{FixError, FixResult};
+use crate::double_double::DoubleDouble;
+use crate::extending::Fixed;
+use crate::fix::fix2x29::{Fix2x29, Fix4x59};
+use crate::fix::fix2x61::Fix2x61;
+use crate::fix::fix4x123::Fix4x123;
+use crate::fix::fixn::{FixN, FixNWide};
+use crate::fix::fixv::{FixV, FixVWide};
+
+// Everything Complex, Point and Set need from a number type.  Arithmetic is checked: fixed point
+// types report overflow as an error, which iteration treats as an escape.  Products are widened
+// into Self::Wide and explicitly truncated back, so fixed point types can keep every bit of a
+// product until it's been added to.
+pub trait MandelbrotNumber:
+    Clone + Debug + Default + PartialEq + Send + Sync + 'static + Neg<Output = Self>
+{
+    type Wide: WideNumber<Narrow = Self>;
+
+    const ZERO: Self;
+    const ONE: Self;
+    const TWO: Self;
+
+    // The number of bits after the binary point that we can rely on for values in [-4, 4).  Only
+    // FixV has a different number for different values.
+    fn precision_bits(&self) -> u32;
+
+    fn power_of_two(pow: i32) -> FixResult<Self>;
+    fn halve(&self) -> FixResult<Self>;
+    // floor(log2(|self|)), or None for zero.
+    fn exponent(&self) -> Option<i32>;
+    // The nearest f64, for when a low precision approximation is all we need.
+    fn to_f64(&self) -> f64;
+    fn from_f64(value: f64) -> FixResult<Self>;
+
+    fn try_add(self, rhs: Self) -> FixResult<Self>;
+    fn try_sub(self, rhs: Self) -> FixResult<Self>;
+    fn widen(self) -> Self::Wide;
+    fn widening_mul(self, rhs: Self) -> Self::Wide;
+
+    fn widening_square(self) -> Self::Wide {
+        self.clone().widening_mul(self)
+    }
+}
+
+pub trait WideNumber: Clone + Debug {
+    type Narrow;
+
+    fn try_add(self, rhs: Self) -> FixResult<Self>;
+    fn try_sub(self, rhs: Self) -> FixResult<Self>;
+    fn truncate(&self) -> FixResult<Self::Narrow>;
+    fn exceeds_four(&self) -> bool;
+    fn is_negative(&self) -> bool;
+    // self / rhs in the narrow type.  Fixed point quotients are rounded down.
+    fn try_div(self, rhs: Self) -> FixResult<Self::Narrow>;
+
+    // The same, but only failing when the result is more than the narrow type can hold at all,
+    // rather than outside [-4, 4).  Fixed point types can't hold anything more, but floats can.
+    fn truncate_far(&self) -> FixResult<Self::Narrow> {
+        self.truncate()
+    }
+
+    fn try_div_far(self, rhs: Self) -> FixResult<Self::Narrow> {
+        self.try_div(rhs)
+    }
+}
+
+// Floats don't need a wider type for products, but we still insist on values staying within the
+// same range as the fixed point types so that everything escapes at the same point.
+macro_rules! float_number {
+    ($t:ty, $precision:literal, $mantissa:literal, $exponent_mask:literal, $bias:literal) => {
+        impl MandelbrotNumber for $t {
+            type Wide = $t;
+
+            const ZERO: Self = 0.0;
+            const ONE: Self = 1.0;
+            const TWO: Self = 2.0;
+
+            fn precision_bits(&self) -> u32 {
+                $precision
+            }
+
+            fn power_of_two(pow: i32) -> FixResult<Self> {
+                let value = (2.0 as $t).powi(pow);
+                if pow > 1 || value == 0.0 {
+                    Err(FixError::Overflow { op: "power_of_two" })
+                } else {
+                    Ok(value)
+                }
+            }
+
+            fn halve(&self) -> FixResult<Self> {
+                let value = self / 2.0;
+                if value == 0.0 {
+                    Err(FixError::Underflow {
+                        op: concat!(stringify!($t), "::halve"),
+                    })
+                } else {
+                    Ok(value)
+                }
+            }
+
+            fn exponent(&self) -> Option<i32> {
+                if *self == 0.0 {
+                    None
+                } else if self.is_normal() {
+                    let biased = (self.to_bits() >> $mantissa) & $exponent_mask;
+                    Some(biased as i32 - $bias)
+                } else {
+                    Some(self.abs().log2().floor() as i32)
+                }
+            }
+
+            fn to_f64(&self) -> f64 {
+                f64::from(*self)
+            }
+
+            fn from_f64(value: f64) -> FixResult<Self> {
+                Ok(value as $t)
+            }
+
+            fn try_add(self, rhs: Self) -> FixResult<Self> {
+                Ok(self + rhs)
+            }
+
+            fn try_sub(self, rhs: Self) -> FixResult<Self> {
+                Ok(self - rhs)
+            }
+
+            fn widen(self) -> Self::Wide {
+                self
+            }
+
+            fn widening_mul(self, rhs: Self) -> Self::Wide {
+                self * rhs
+            }
+        }
+
+        impl WideNumber for $t {
+            type Narrow = $t;
+
+            fn try_add(self, rhs: Self) -> FixResult<Self> {
+                Ok(self + rhs)
+            }
+
+            fn try_sub(self, rhs: Self) -> FixResult<Self> {
+                Ok(self - rhs)
+            }
+
+            fn truncate(&self) -> FixResult<Self> {
+                if self.exceeds_four() {
+                    Err(FixError::Overflow {
+                        op: concat!(stringify!($t), "::truncate"),
+                    })
+                } else {
+                    Ok(*self)
+                }
+            }
+
+            fn exceeds_four(&self) -> bool {
+                self.is_nan() || self.abs() >= 4.0
+            }
+
+            fn is_negative(&self) -> bool {
+                *self < 0.0
+            }
+
+            fn try_div(self, rhs: Self) -> FixResult<Self> {
+                (self / rhs).truncate()
+            }
+
+            fn truncate_far(&self) -> FixResult<Self> {
+                if self.is_finite() {
+                    Ok(*self)
+                } else {
+                    Err(FixError::Overflow {
+                        op: concat!(stringify!($t), "::truncate_far"),
+                    })
+                }
+            }
+
+            fn try_div_far(self, rhs: Self) -> FixResult<Self> {
+                (self / rhs).truncate_far()
+            }
+        }
+    };
+}
+
+float_number!(f32, 21, 23, 0xff, 127);
+float_number!(f64, 50, 52, 0x7ff, 1023);
+
+impl MandelbrotNumber for DoubleDouble {
+    type Wide = DoubleDouble;
+
+    const ZERO: Self = DoubleDouble::ZERO;
+    const ONE: Self = DoubleDouble::new(1.0);
+    const TWO: Self = DoubleDouble::TWO;
+
+    // 106 bits of mantissa, less a couple for values up to 4 and a couple more for the error
+    // bounds on double-double addition.
+    fn precision_bits(&self) -> u32 {
+        100
+    }
+
+    fn power_of_two(pow: i32) -> FixResult<Self> {
+        <f64 as MandelbrotNumber>::power_of_two(pow).map(DoubleDouble::new)
+    }
+
+    fn halve(&self) -> FixResult<Self> {
+        if self.hi.abs() < f64::MIN_POSITIVE * 2.0 {
+            Err(FixError::Underflow {
+                op: "DoubleDouble::halve",
+            })
+        } else {
+            Ok(self.scale(-1))
+        }
+    }
+
+    fn exponent(&self) -> Option<i32> {
+        let exponent = self.hi.exponent()?;
+        // If hi is an exact power of two, a lo of the opposite sign takes us just below it.
+        if self.hi.abs() == 2f64.powi(exponent)
+            && self.lo != 0.0
+            && self.lo.signum() != self.hi.signum()
+        {
+            Some(exponent - 1)
+        } else {
+            Some(exponent)
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.hi
+    }
+
+    fn from_f64(value: f64) -> FixResult<Self> {
+        Ok(DoubleDouble::new(value))
+    }
+
+    fn try_add(self, rhs: Self) -> FixResult<Self> {
+        Ok(self + rhs)
+    }
+
+    fn try_sub(self, rhs: Self) -> FixResult<Self> {
+        Ok(self - rhs)
+    }
+
+    fn widen(self) -> Self::Wide {
+        self
+    }
+
+    fn widening_mul(self, rhs: Self) -> Self::Wide {
+        self * rhs
+    }
+}
+
+impl WideNumber for DoubleDouble {
+    type Narrow = DoubleDouble;
+
+    fn try_add(self, rhs: Self) -> FixResult<Self> {
+        Ok(self + rhs)
+    }
+
+    fn try_sub(self, rhs: Self) -> FixResult<Self> {
+        Ok(self - rhs)
+    }
+
+    fn truncate(&self) -> FixResult<Self> {
+        if self.exceeds_four() {
+            Err(FixError::Overflow {
+                op: "DoubleDouble::truncate",
+            })
+        } else {
+            Ok(*self)
+        }
+    }
+
+    fn exceeds_four(&self) -> bool {
+        self.exceeds(4.0)
+    }
+
+    fn is_negative(&self) -> bool {
+        self.hi < 0.0 || (self.hi == 0.0 && self.lo < 0.0)
+    }
+
+    fn try_div(self, rhs: Self) -> FixResult<Self> {
+        (self / rhs).truncate()
+    }
+
+    fn truncate_far(&self) -> FixResult<Self> {
+        if self.hi.is_finite() && self.lo.is_finite() {
+            Ok(*self)
+        } else {
+            Err(FixError::Overflow {
+                op: "DoubleDouble::truncate_far",
+            })
+        }
+    }
+
+    fn try_div_far(self, rhs: Self) -> FixResult<Self> {
+        (self / rhs).truncate_far()
+    }
+}
+
+// Fixed point types keep every bit of a product in a type with twice the storage, one more integer
+// bit, and twice the fractional bits plus one.
+macro_rules! fixed_number {
+    ($narrow:ty, $wide:ty) => {
+        impl MandelbrotNumber for $narrow {
+            type Wide = $wide;
+
+            const ZERO: Self = <$narrow>::ZERO;
+            const ONE: Self = Fixed(1 << <$narrow>::FRACTIONAL_BITS);
+            const TWO: Self = Fixed(2 << <$narrow>::FRACTIONAL_BITS);
+
+            fn precision_bits(&self) -> u32 {
+                <$narrow>::FRACTIONAL_BITS
+            }
+
+            fn power_of_two(pow: i32) -> FixResult<Self> {
+                <$narrow>::try_power_of_two(pow)
+            }
+
+            fn halve(&self) -> FixResult<Self> {
+                Fixed::halve(self)
+            }
+
+            fn exponent(&self) -> Option<i32> {
+                Fixed::exponent(self)
+            }
+
+            fn to_f64(&self) -> f64 {
+                Fixed::to_f64(self)
+            }
+
+            fn from_f64(value: f64) -> FixResult<Self> {
+                <$narrow>::try_from(value)
+            }
+
+            #[inline]
+            fn try_add(self, rhs: Self) -> FixResult<Self> {
+                Fixed::try_add(self, rhs)
+            }
+
+            #[inline]
+            fn try_sub(self, rhs: Self) -> FixResult<Self> {
+                Fixed::try_sub(self, rhs)
+            }
+
+            #[inline]
+            fn widen(self) -> Self::Wide {
+                Fixed::widen(self)
+            }
+
+            #[inline]
+            fn widening_mul(self, rhs: Self) -> Self::Wide {
+                Fixed::widening_mul(self, rhs)
+            }
+        }
+
+        impl WideNumber for $wide {
+            type Narrow = $narrow;
+
+            #[inline]
+            fn try_add(self, rhs: Self) -> FixResult<Self> {
+                Fixed::try_add(self, rhs)
+            }
+
+            #[inline]
+            fn try_sub(self, rhs: Self) -> FixResult<Self> {
+                Fixed::try_sub(self, rhs)
+            }
+
+            #[inline]
+            fn truncate(&self) -> FixResult<$narrow> {
+                if self.exceeds_four() {
+                    Err(FixError::Overflow { op: "truncate" })
+                } else {
+                    let shift = <$wide>::FRACTIONAL_BITS - <$narrow>::FRACTIONAL_BITS;
+                    Ok(Fixed((self.0 >> shift) as _))
+                }
+            }
+
+            #[inline]
+            fn exceeds_four(&self) -> bool {
+                let four = 4 << <$wide>::FRACTIONAL_BITS;
+                self.0 >= four || self.0 <= -four
+            }
+
+            #[inline]
+            fn is_negative(&self) -> bool {
+                Fixed::is_negative(self)
+            }
+
+            fn try_div(self, rhs: Self) -> FixResult<$narrow> {
+                match Fixed::checked_div(self, rhs) {
+                    Some(quotient) => quotient.truncate(),
+                    None => Err(FixError::Overflow { op: "try_div" }),
+                }
+            }
+        }
+    };
+}
+
+fixed_number!(Fix2x61, Fix4x123);
+fixed_number!(Fix2x29, Fix4x59);
+
+impl<const LIMBS: usize> MandelbrotNumber for FixN<LIMBS> {
+    type Wide = FixNWide<LIMBS>;
+
+    const ZERO: Self = FixN::zero();
+    const ONE: Self = FixN::one();
+    const TWO: Self = FixN::two();
+
+    fn precision_bits(&self) -> u32 {
+        FixN::<LIMBS>::FRACTIONAL_BITS as u32
+    }
+
+    fn power_of_two(pow: i32) -> FixResult<Self> {
+        FixN::power_of_two(pow)
+    }
+
+    fn halve(&self) -> FixResult<Self> {
+        FixN::halve(self)
+    }
+
+    fn exponent(&self) -> Option<i32> {
+        FixN::exponent(self)
+    }
+
+    fn to_f64(&self) -> f64 {
+        FixN::to_f64(self)
+    }
+
+    fn from_f64(value: f64) -> FixResult<Self> {
+        FixN::try_from(value)
+    }
+
+    fn try_add(self, rhs: Self) -> FixResult<Self> {
+        self + rhs
+    }
+
+    fn try_sub(self, rhs: Self) -> FixResult<Self> {
+        self - rhs
+    }
+
+    fn widen(self) -> Self::Wide {
+        From::from(self)
+    }
+
+    fn widening_mul(self, rhs: Self) -> Self::Wide {
+        self * rhs
+    }
+}
+
+impl<const LIMBS: usize> WideNumber for FixNWide<LIMBS> {
+    type Narrow = FixN<LIMBS>;
+
+    fn try_add(self, rhs: Self) -> FixResult<Self> {
+        self + rhs
+    }
+
+    fn try_sub(self, rhs: Self) -> FixResult<Self> {
+        self - rhs
+    }
+
+    fn truncate(&self) -> FixResult<FixN<LIMBS>> {
+        FixNWide::truncate(self)
+    }
+
+    fn exceeds_four(&self) -> bool {
+        FixNWide::exceeds_four(self)
+    }
+
+    fn is_negative(&self) -> bool {
+        FixNWide::is_negative(self)
+    }
+
+    fn try_div(self, rhs: Self) -> FixResult<FixN<LIMBS>> {
+        self.checked_div(&rhs)
+    }
+}
+
+impl MandelbrotNumber for FixV {
+    type Wide = FixVWide;
+
+    const ZERO: Self = FixV::ZERO;
+    const ONE: Self = FixV::ONE;
+    const TWO: Self = FixV::TWO;
+
+    fn precision_bits(&self) -> u32 {
+        self.fractional_bits()
+    }
+
+    fn power_of_two(pow: i32) -> FixResult<Self> {
+        FixV::power_of_two(pow)
+    }
+
+    fn halve(&self) -> FixResult<Self> {
+        FixV::halve(self)
+    }
+
+    fn exponent(&self) -> Option<i32> {
+        FixV::exponent(self)
+    }
+
+    fn to_f64(&self) -> f64 {
+        FixV::to_f64(self)
+    }
+
+    fn from_f64(value: f64) -> FixResult<Self> {
+        FixV::from_f64(value)
+    }
+
+    fn try_add(self, rhs: Self) -> FixResult<Self> {
+        FixV::try_add(self, rhs)
+    }
+
+    fn try_sub(self, rhs: Self) -> FixResult<Self> {
+        FixV::try_sub(self, rhs)
+    }
+
+    fn widen(self) -> Self::Wide {
+        FixV::widen(&self)
+    }
+
+    fn widening_mul(self, rhs: Self) -> Self::Wide {
+        FixV::widening_mul(self, rhs)
+    }
+}
+
+impl WideNumber for FixVWide {
+    type Narrow = FixV;
+
+    fn try_add(self, rhs: Self) -> FixResult<Self> {
+        FixVWide::try_add(self, rhs)
+    }
+
+    fn try_sub(self, rhs: Self) -> FixResult<Self> {
+        FixVWide::try_sub(self, rhs)
+    }
+
+    fn truncate(&self) -> FixResult<FixV> {
+        FixVWide::truncate(self)
+    }
+
+    fn exceeds_four(&self) -> bool {
+        FixVWide::exceeds_four(self)
+    }
+
+    fn is_negative(&self) -> bool {
+        FixVWide::is_negative(self)
+    }
+
+    fn try_div(self, rhs: Self) -> FixResult<FixV> {
+        self.checked_div(&rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::fix::fix2x29::Fix2x29;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::fix::fixn::FixN;
+    use crate::point::Point;
+
+    use super::MandelbrotNumber;
+
+    // Points that don't escape can find their cycles at different times in different types.
+    fn escape_count<N: MandelbrotNumber>(r: N, i: N) -> Result<Option<u64>, Error> {
+        let mut p = Point::new(Complex::new(r, i));
+        p.iterate_n(1000)?;
+        Ok(Some(p.iterations).filter(|_| p.escaped))
+    }
+
+    #[test]
+    fn exponents() {
+        assert_eq!(MandelbrotNumber::exponent(&Fix2x61::one()), Some(0));
+        assert_eq!(MandelbrotNumber::exponent(&-Fix2x61::two()), Some(1));
+        assert_eq!(MandelbrotNumber::exponent(&Fix2x61::zero()), None);
+        assert_eq!(
+            MandelbrotNumber::exponent(&FixN::<4>::power_of_two(-200).unwrap()),
+            Some(-200)
+        );
+        assert_eq!(MandelbrotNumber::exponent(&0.75f64), Some(-1));
+        assert_eq!(MandelbrotNumber::exponent(&-3f32), Some(1));
+        assert_eq!(MandelbrotNumber::exponent(&1e-310f64), Some(-1030));
+    }
+
+    #[test]
+    fn backends_agree_on_escape_counts() -> Result<(), Error> {
+        for &(r, i) in [
+            (0.3, 0.5),
+            (-0.75, 0.1),
+            (-1.5, 0.0),
+            (0.25, 0.5),
+            (-2.0, 1.0),
+        ]
+        .iter()
+        {
+            let fixed = escape_count(Fix2x61::try_from(r)?, Fix2x61::try_from(i)?)?;
+            let wide = escape_count(FixN::<3>::try_from(r)?, FixN::<3>::try_from(i)?)?;
+            let double = escape_count(r, i)?;
+            let single = escape_count(r as f32, i as f32)?;
+            let narrow = escape_count(Fix2x29::try_from(r)?, Fix2x29::try_from(i)?)?;
+            assert_eq!(fixed, wide, "{}, {}", r, i);
+            assert_eq!(fixed, narrow, "{}, {}", r, i);
+            assert_eq!(fixed, double, "{}, {}", r, i);
+            assert_eq!(fixed, single, "{}, {}", r, i);
+        }
+        Ok(())
+    }
+}
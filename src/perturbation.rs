@@ -0,0 +1,269 @@
+use anyhow::{bail, Context, Error};
+use rayon::prelude::*;
+
+use crate::complex::{Complex, FixError};
+use crate::number::MandelbrotNumber;
+use crate::point::Point;
+use crate::set::Set;
+
+// Pauldelbrot's criterion: once |z|^2 falls below this fraction of |Z|^2 the delta has cancelled
+// away most of the reference, and the bits it has left aren't enough to trust.
+const GLITCH_TOLERANCE: f64 = 1e-6;
+
+// How many times we'll pick a new reference for glitched points before falling back to iterating
+// whatever's left at full precision.
+const MAX_REFERENCES: usize = 32;
+
+// The orbit of a single point, iterated at full precision and then kept as f64s.  Deltas against
+// it stay small enough that f64 is plenty, down to the limit of f64's exponent (about 10^-300).
+pub struct ReferenceOrbit {
+    orbit: Vec<Complex<f64>>,
+}
+
+impl ReferenceOrbit {
+    pub fn compute<N: MandelbrotNumber>(c: &Complex<N>, max_iter: u64) -> Result<Self, Error> {
+        let mut orbit = vec![to_f64(c)];
+        let mut z = c.clone();
+        for _ in 0..max_iter {
+            match z.iterate_mandelbrot(c) {
+                Ok(()) => orbit.push(to_f64(&z)),
+                Err(FixError::Escaped) => {
+                    // Keep an approximation of the value that escaped, so that points near the
+                    // reference can escape alongside it.
+                    let (last, c) = (orbit[orbit.len() - 1], to_f64(c));
+                    orbit.push(Complex::new(
+                        last.r * last.r - last.i * last.i + c.r,
+                        2.0 * last.r * last.i + c.i,
+                    ));
+                    break;
+                }
+                Err(e) => return Err(e).context("Computing reference orbit"),
+            }
+        }
+        Ok(ReferenceOrbit { orbit })
+    }
+
+    pub fn len(&self) -> usize {
+        self.orbit.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orbit.is_empty()
+    }
+
+    // Iterates the point at reference + dc, with both z and dz starting at dc in the same way that
+    // Point starts with its value at its location.
+    pub fn iterate_delta(&self, dc: Complex<f64>, max_iter: u64) -> Perturbed {
+        let orbit = &self.orbit;
+        let (mut dr, mut di) = (dc.r, dc.i);
+        for n in 0..max_iter {
+            let k = n as usize;
+            if k + 1 >= orbit.len() {
+                // The reference escaped before this point did.
+                return Perturbed::Glitched(f64::INFINITY);
+            }
+
+            // dz' = 2 Z dz + dz^2 + dc
+            let z = orbit[k];
+            let (r, i) = (
+                2.0 * (z.r * dr - z.i * di) + (dr * dr - di * di) + dc.r,
+                2.0 * (z.r * di + z.i * dr) + 2.0 * dr * di + dc.i,
+            );
+            dr = r;
+            di = i;
+
+            let reference = orbit[k + 1];
+            let (r, i) = (reference.r + dr, reference.i + di);
+            let norm_sqr = r * r + i * i;
+            if norm_sqr >= 4.0 {
+                return Perturbed::Escaped(n + 1);
+            }
+            let reference_norm_sqr = reference.r * reference.r + reference.i * reference.i;
+            if norm_sqr < GLITCH_TOLERANCE * reference_norm_sqr {
+                return Perturbed::Glitched(norm_sqr / reference_norm_sqr);
+            }
+        }
+        Perturbed::Interior
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Perturbed {
+    Escaped(u64),
+    Interior,
+    // How far |z|^2 had fallen relative to |Z|^2 when we gave up, or infinity if the reference
+    // escaped first.  The smallest ratio marks the best candidate for the next reference.
+    Glitched(f64),
+}
+
+fn to_f64<N: MandelbrotNumber>(c: &Complex<N>) -> Complex<f64> {
+    Complex::new(c.r.to_f64(), c.i.to_f64())
+}
+
+fn delta<N: MandelbrotNumber>(p: &Point<N>, reference: &Complex<N>) -> Result<Complex<f64>, Error> {
+    let loc = p.loc();
+    Ok(Complex::new(
+        loc.r.clone().try_sub(reference.r.clone())?.to_f64(),
+        loc.i.clone().try_sub(reference.i.clone())?.to_f64(),
+    ))
+}
+
+impl<N: MandelbrotNumber> Set<N> {
+    // Iterates every point to max_iter as a delta from one or more reference orbits, rather than
+    // at full precision.  The first reference is the centre of the set; points that glitch are
+    // retried against a reference picked from among themselves.
+    pub fn iterate_perturbed(&mut self, max_iter: u64, verbose: bool) -> Result<(), Error> {
+        if !self.is_mandelbrot() {
+            bail!("Perturbation only supports the Mandelbrot set");
+        }
+        let mut pending: Vec<usize> = (0..self.points.len()).collect();
+        let mut reference = self.centre.clone();
+        let mut references = 0;
+
+        while !pending.is_empty() && references < MAX_REFERENCES {
+            references += 1;
+            let orbit = ReferenceOrbit::compute(&reference, max_iter)?;
+            let outcomes: Result<Vec<Perturbed>, Error> = pending
+                .par_iter()
+                .map(|&i| Ok(orbit.iterate_delta(delta(&self.points[i], &reference)?, max_iter)))
+                .collect();
+
+            let mut glitched = vec![];
+            let mut best: Option<(f64, usize)> = None;
+            for (&i, outcome) in pending.iter().zip(outcomes?) {
+                let p = &mut self.points[i];
+                p.escape_candidate = true;
+                match outcome {
+                    Perturbed::Escaped(n) => {
+                        p.escaped = true;
+                        p.iterations = n;
+                    }
+                    Perturbed::Interior => {
+                        p.escaped = false;
+                        p.iterations = max_iter;
+                    }
+                    Perturbed::Glitched(ratio) => {
+                        if best.is_none_or(|(b, _)| ratio < b) {
+                            best = Some((ratio, i));
+                        }
+                        glitched.push(i);
+                    }
+                }
+            }
+
+            if verbose {
+                println!(
+                    "Reference {} reached {} iterations, {} of {} points glitched",
+                    references,
+                    orbit.len(),
+                    glitched.len(),
+                    pending.len()
+                );
+            }
+
+            if let Some((_, i)) = best {
+                reference = self.points[i].loc().clone();
+            }
+            pending = glitched;
+        }
+
+        if verbose && !pending.is_empty() {
+            println!(
+                "Iterating {} points at full precision after {} references",
+                pending.len(),
+                references
+            );
+        }
+        for i in pending {
+            let p = &mut self.points[i];
+            *p = Point::new(p.loc().clone());
+            p.escape_candidate = true;
+            p.iterate_n(max_iter)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::fix::fixn::FixN;
+    use crate::set::Set;
+
+    use super::{Perturbed, ReferenceOrbit};
+
+    fn brute_force<N: crate::number::MandelbrotNumber>(
+        set: &mut Set<N>,
+        max_iter: u64,
+    ) -> Result<(), Error> {
+        for p in set.points.iter_mut() {
+            p.iterate_n(max_iter)?;
+        }
+        Ok(())
+    }
+
+    fn agreement<N>(a: &Set<N>, b: &Set<N>) -> f64 {
+        let same = a
+            .points
+            .iter()
+            .zip(b.points.iter())
+            .filter(|(a, b)| a.escaped == b.escaped && (!a.escaped || a.iterations == b.iterations))
+            .count();
+        same as f64 / a.points.len() as f64
+    }
+
+    #[test]
+    fn reference_point_matches_its_own_orbit() -> Result<(), Error> {
+        let c = Complex::new(-0.75f64, 0.1);
+        let orbit = ReferenceOrbit::compute(&c, 1000)?;
+        let outcome = orbit.iterate_delta(Complex::zero(), 1000);
+        assert_eq!(outcome, Perturbed::Escaped(orbit.len() as u64 - 1));
+        Ok(())
+    }
+
+    #[test]
+    fn matches_brute_force() -> Result<(), Error> {
+        let centre = Complex::new(Fix2x61::try_from(-0.75)?, Fix2x61::try_from(0.1)?);
+        let radius = Fix2x61::power_of_two(-4)?;
+        let mut perturbed = Set::create(5, centre, radius)?;
+        let mut direct = Set::create(5, centre, radius)?;
+        perturbed.iterate_perturbed(2000, false)?;
+        brute_force(&mut direct, 2000)?;
+        assert!(agreement(&perturbed, &direct) > 0.99);
+        Ok(())
+    }
+
+    #[test]
+    fn re_references_when_centre_escapes_early() -> Result<(), Error> {
+        // The centre of this view escapes almost immediately, while much of the rest is interior.
+        let centre = Complex::new(Fix2x61::try_from(-0.5)?, Fix2x61::try_from(0.625)?);
+        let radius = Fix2x61::power_of_two(-3)?;
+        let mut perturbed = Set::create(4, centre, radius)?;
+        let mut direct = Set::create(4, centre, radius)?;
+        perturbed.iterate_perturbed(500, false)?;
+        brute_force(&mut direct, 500)?;
+        assert!(agreement(&perturbed, &direct) > 0.99);
+        Ok(())
+    }
+
+    #[test]
+    fn deep_zoom() -> Result<(), Error> {
+        // Just outside the cusp of the main cardioid, where points take around pi / sqrt(2^-16)
+        // iterations to escape, and far deeper than f64 can go on its own.
+        let cusp = (FixN::<2>::try_from(0.25)? + FixN::power_of_two(-16)?)?;
+        let centre = Complex::new(cusp, FixN::zero());
+        let radius = FixN::power_of_two(-70)?;
+        let mut direct = Set::create(3, centre, radius)?;
+        let mut perturbed = Set::create(3, centre, radius)?;
+        perturbed.iterate_perturbed(3000, false)?;
+        brute_force(&mut direct, 3000)?;
+        assert!(direct.points.iter().any(|p| p.escaped));
+        assert!(agreement(&perturbed, &direct) > 0.99);
+        Ok(())
+    }
+}
@@ -2,52 +2,180 @@ use anyhow::{Context, Error};
 
 use crate::complex::{Complex, FixError};
 use crate::fix::fix2x61::Fix2x61;
+use crate::formula::{Formula, Iteration};
+use crate::number::MandelbrotNumber;
+
+// Iterating far enough past escape in f64 for distance estimates to be accurate.
+const DISTANCE_RADIUS_SQR: f64 = 1e6;
+const MAX_DISTANCE_STEPS: u32 = 64;
+
+#[derive(Copy, Clone, Debug)]
+struct Derivative {
+    dz: Complex<f64>,
+    julia: bool,
+}
 
 #[derive(Copy, Clone, Debug)]
-pub struct Point {
-    loc: Complex,
-    value: Complex,
+pub struct Point<N = Fix2x61> {
+    loc: Complex<N>,
+    value: Complex<N>,
+    // Anything else the formula needs to remember between iterations.
+    state: Complex<N>,
     pub iterations: u64,
     pub escaped: bool,
+    // Which attractor it settled on instead of escaping, for the formulas that have them.
+    pub attractor: Option<u8>,
     pub escape_candidate: bool,
+    pub(crate) formula: Formula,
+    // For cycle detection: a value from earlier in the orbit, and when we saw it.
+    saved: Complex<N>,
+    saved_at: u64,
+    // Set once the orbit comes back round to a value it's had before.  Iterating is
+    // deterministic, so from then on it repeats forever and can never escape.
+    pub period: Option<u64>,
+    // When estimating distances, dz/dc so far, or dz/dz0 for Julia sets.  Derivatives soon grow
+    // far beyond the range of the fixed point types, so they're kept in f64.
+    derivative: Option<Derivative>,
+    // |z| and |dz| once the point escaped, if we were following the derivative.
+    pub escape_sizes: Option<(f64, f64)>,
 }
 
-impl Point {
-    pub const ORIGIN: Point = Point::new(Complex::new(Fix2x61::zero(), Fix2x61::zero()));
+impl<N: MandelbrotNumber> Point<N> {
+    pub fn origin() -> Point<N> {
+        Point::new(Complex::zero())
+    }
+
+    pub fn from_parts(x: &N, y: &N) -> Point<N> {
+        Point::new(Complex::new(x.clone(), y.clone()))
+    }
 
-    pub fn from_parts(x: &Fix2x61, y: &Fix2x61) -> Point {
-        Point::new(Complex::new(*x, *y))
+    pub fn new(c: Complex<N>) -> Point<N> {
+        Point::julia(c.clone(), c)
     }
 
-    pub const fn new(c: Complex) -> Point {
+    // A point of the parameter plane for a formula, starting where it starts its orbits.
+    pub fn with_formula(c: Complex<N>, formula: Formula) -> Point<N> {
+        let mut point = Point::new(c);
+        point.formula = formula;
+        point.restart();
+        point
+    }
+
+    // A point of the Julia set for c: it starts at z and adds c on every iteration.  The
+    // Mandelbrot set is the special case where the two are the same.
+    pub fn julia(z: Complex<N>, c: Complex<N>) -> Point<N> {
         let escaped = false;
         let escape_candidate = false;
         Point {
             loc: c,
-            value: c,
+            value: z.clone(),
+            state: Complex::zero(),
             iterations: 0,
             escaped,
+            attractor: None,
             escape_candidate,
+            formula: Formula::MANDELBROT,
+            saved: z,
+            saved_at: 0,
+            period: None,
+            derivative: None,
+            escape_sizes: None,
         }
     }
 
     // Microbenchmarks suggest no benefit from an inline attribute
     pub fn iterate(&mut self) -> Result<(), Error> {
-        if !self.escaped {
-            let iterated = self.value.iterate_mandelbrot(&self.loc);
-            if let Err(FixError::Escaped) = iterated {
-                self.escaped = true;
-            } else {
-                iterated.with_context(|| format!("Iterating, value after: {:?}", self.value))?
+        if !self.settled() {
+            let before = self
+                .derivative
+                .map(|_| Complex::new(self.value.r.to_f64(), self.value.i.to_f64()));
+            let iterated = self
+                .formula
+                .iterate(&mut self.value, &mut self.state, &self.loc);
+            match iterated {
+                Err(FixError::Escaped) => self.escaped = true,
+                Err(FixError::Converged { attractor }) => self.attractor = Some(attractor),
+                _ => {
+                    iterated.with_context(|| format!("Iterating, value after: {:?}", self.value))?
+                }
             }
             self.iterations += 1;
+            if let Some(before) = before {
+                self.follow_derivative(before);
+            }
+            if !self.finished() {
+                self.check_period();
+            }
         }
         Ok(())
     }
 
+    // Brent's cycle detection: each time the iteration count reaches a power of two we save the
+    // value, and compare every value after it against that.  Any cycle is found by the time the
+    // gap between saves is longer than it.  Formulas with state of their own would need that
+    // comparing too, so we leave them be.
+    fn check_period(&mut self) {
+        if self.value == self.saved && !self.formula.uses_state() {
+            self.period = Some(self.iterations - self.saved_at);
+        } else if self.iterations.is_power_of_two() {
+            self.saved = self.value.clone();
+            self.saved_at = self.iterations;
+        }
+    }
+
+    // Starts following the derivative, so that we can estimate how far the point is from the set
+    // once it escapes.  Only the Multibrot formulas have one, and only from the start.
+    pub fn track_derivative(&mut self, julia: bool) {
+        self.derivative = Some(Derivative {
+            dz: Complex::new(1.0, 0.0),
+            julia,
+        });
+    }
+
+    fn follow_derivative(&mut self, before: Complex<f64>) {
+        let exponent = match self.formula {
+            Formula::Multibrot { exponent } => exponent,
+            _ => return,
+        };
+        if let Some(derivative) = self.derivative.as_mut() {
+            let c = Complex::new(self.loc.r.to_f64(), self.loc.i.to_f64());
+            let step =
+                |z: Complex<f64>, dz| z.iterate_with_derivative(dz, c, exponent, !derivative.julia);
+            let (mut z, mut dz) = step(before, derivative.dz);
+            if !self.escaped {
+                derivative.dz = dz;
+                return;
+            }
+            // Stopping as soon as |z| passes 2 makes for a poor estimate, so we carry on a little
+            // further where f64 has room.
+            for _ in 0..MAX_DISTANCE_STEPS {
+                if z.magnitude_sqr() > DISTANCE_RADIUS_SQR {
+                    break;
+                }
+                (z, dz) = step(z, dz);
+            }
+            self.escape_sizes = Some((z.magnitude_sqr().sqrt(), dz.magnitude_sqr().sqrt()));
+        }
+    }
+
+    // Roughly how far an escaped point is from the set, from |z| ln |z| / |dz|.
+    pub fn distance(&self) -> Option<f64> {
+        self.escape_sizes.map(|(z, dz)| z * z.ln() / dz)
+    }
+
+    // There's nothing more to learn by iterating.
+    pub fn finished(&self) -> bool {
+        self.escaped || self.attractor.is_some()
+    }
+
+    // Either finished, or known never to finish.
+    pub fn settled(&self) -> bool {
+        self.finished() || self.period.is_some()
+    }
+
     pub fn iterate_n(&mut self, n: u64) -> Result<(), Error> {
         for i in 0..n {
-            if self.escaped {
+            if self.settled() {
                 return Ok(());
             }
             self.iterate().with_context(|| {
@@ -67,7 +195,7 @@ impl Point {
 
         let start = self.iterations;
         for i in start..n {
-            if self.escaped {
+            if self.settled() {
                 return Ok(());
             }
             self.iterate().with_context(|| {
@@ -80,9 +208,55 @@ impl Point {
         Ok(())
     }
 
-    pub fn value(&self) -> &Complex {
+    pub fn value(&self) -> &Complex<N> {
         &self.value
     }
+
+    pub fn loc(&self) -> &Complex<N> {
+        &self.loc
+    }
+
+    // Goes back to the start of the orbit of a point of the parameter plane, for when its formula
+    // changes before it's been iterated.
+    pub(crate) fn restart(&mut self) {
+        self.value = self.formula.start(self.loc.clone());
+        self.saved = self.value.clone();
+    }
+
+    // Jumps straight to a value we've found some other way, as if we'd iterated to it.
+    pub(crate) fn skip_to(&mut self, value: Complex<N>, iterations: u64) {
+        self.value = value.clone();
+        self.iterations = iterations;
+        self.saved = value;
+        self.saved_at = iterations;
+        // We don't know how the derivative changed along the way.
+        self.derivative = None;
+    }
+
+    // The same point, with its progress so far, in a different number type.  A cycle in one
+    // type's arithmetic needn't be a cycle in another's, so we look for one afresh.
+    // `number` does the conversion.
+    pub fn convert<M, E>(&self, number: impl Fn(&N) -> Result<M, E>) -> Result<Point<M>, E>
+    where
+        M: MandelbrotNumber,
+    {
+        let value = self.value.convert_with(&number)?;
+        Ok(Point {
+            loc: self.loc.convert_with(&number)?,
+            value: value.clone(),
+            state: self.state.convert_with(&number)?,
+            iterations: self.iterations,
+            escaped: self.escaped,
+            attractor: self.attractor,
+            escape_candidate: self.escape_candidate,
+            formula: self.formula,
+            saved: value,
+            saved_at: self.iterations,
+            period: None,
+            derivative: self.derivative,
+            escape_sizes: self.escape_sizes,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -109,25 +283,79 @@ mod test {
 
     #[test]
     fn zero_never_escapes() -> Result<(), Error> {
-        let mut zero: Point = Point::ORIGIN;
-        let target_count = 1_000_000;
-        zero.iterate_n(target_count)?;
+        let mut zero: Point = Point::origin();
+        zero.iterate_n(1_000_000)?;
 
         assert!(!zero.escaped);
-        assert_eq!(zero.iterations, target_count);
+        assert_eq!(zero.period, Some(1));
+        assert_eq!(zero.iterations, 1);
         Ok(())
     }
 
+    // The orbit of the cusp creeps towards 1/2 without ever quite repeating.
+    fn cusp() -> Result<Point, Error> {
+        Ok(Point::from_parts(&(0.25).try_into()?, &Fix2x61::zero()))
+    }
+
     #[test]
     fn iterate_to_works() -> Result<(), Error> {
-        let mut zero: Point = Point::ORIGIN;
-        zero.escape_candidate = true;
-        zero.iterate_n(10)?;
+        let mut cusp = cusp()?;
+        cusp.escape_candidate = true;
+        cusp.iterate_n(10)?;
         let target_count = 1_000_000;
-        zero.iterate_to_n(target_count)?;
+        cusp.iterate_to_n(target_count)?;
 
-        assert!(!zero.escaped);
-        assert_eq!(zero.iterations, target_count);
+        assert!(!cusp.escaped);
+        assert_eq!(cusp.period, None);
+        assert_eq!(cusp.iterations, target_count);
+        Ok(())
+    }
+
+    #[test]
+    fn interior_orbits_repeat() -> Result<(), Error> {
+        let period = |r: f64, i: f64| -> Result<(Option<u64>, u64), Error> {
+            let mut p: Point = Point::from_parts(&r.try_into()?, &i.try_into()?);
+            p.iterate_n(1_000_000)?;
+            assert!(!p.escaped);
+            Ok((p.period, p.iterations))
+        };
+        for &(r, i, expected) in &[(-0.1, 0.1, 1), (-1.0, 0.0, 2), (-1.1, 0.1, 2)] {
+            let (found, iterations) = period(r, i)?;
+            assert_eq!(found, Some(expected), "{} {}", r, i);
+            assert!(iterations < 10_000, "{} {}: {}", r, i, iterations);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn estimates_distances() -> Result<(), Error> {
+        let estimate = |mut p: Point| -> Result<Option<f64>, Error> {
+            p.track_derivative(false);
+            p.iterate_n(1_000)?;
+            Ok(p.distance())
+        };
+        // Points to the right of the cusp and beyond the tip of the antenna.  Close in to the cusp
+        // the estimate is much too small, as orbits there take so long to leave.
+        for &(r, distance) in &[(0.5, 0.25), (-2.1, 0.1)] {
+            let found = estimate(Point::from_parts(&r.try_into()?, &Fix2x61::zero()))?.unwrap();
+            assert!(
+                found > distance / 4.0 && found < distance * 4.0,
+                "{}: {}",
+                r,
+                found
+            );
+        }
+        assert_eq!(estimate(Point::origin())?, None);
+
+        // The filled Julia set for c = 0 is the unit disc.
+        let mut p: Point = Point::julia(
+            Complex::new((1.5).try_into()?, Fix2x61::zero()),
+            Complex::zero(),
+        );
+        p.track_derivative(true);
+        p.iterate_n(1_000)?;
+        let found = p.distance().unwrap();
+        assert!((found - 0.5).abs() < 0.2, "{}", found);
         Ok(())
     }
 
@@ -155,4 +383,18 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn julia_adds_c() -> Result<(), Error> {
+        let c = Complex::new(-Fix2x61::one(), (0.5).try_into()?);
+        let mut p: Point = Point::julia(Complex::new((0.5).try_into()?, Fix2x61::zero()), c);
+        p.iterate()?;
+
+        assert_eq!(
+            p.value,
+            Complex::new((-0.75).try_into()?, (0.5).try_into()?)
+        );
+        assert_eq!(*p.loc(), c);
+        Ok(())
+    }
 }
@@ -0,0 +1,399 @@
+use std::any::type_name;
+
+use anyhow::Error;
+
+use crate::colours::ColourScheme;
+use crate::complex::{Complex, FixError};
+use crate::double_double::DoubleDouble;
+use crate::fix::fix2x61::Fix2x61;
+use crate::fix::fixn::FixN;
+use crate::fix::fixv::FixV;
+use crate::formula::Formula;
+use crate::set::{quad_view, Orientation, Seeding, Set, Strategy};
+use crate::zoom_path::Quad;
+
+// A Set that moves itself into a wider number type whenever zooming in would take it past the
+// precision of the type it's currently using.  Shallow views start out in f64, which is much faster
+// than fixed point.  Past FixN<16> we use FixV, doubling its limbs each time it runs out, so zooms
+// can go as deep as there's memory for.
+// There's only ever one of these, so the wide variants being large doesn't matter.
+#[allow(clippy::large_enum_variant)]
+pub enum PromotingSet {
+    // f64 can't hold every centre or Julia parameter exactly, so we follow the view in Fix2x61 as
+    // well, for when we move on to fixed point.
+    F64(Set<f64>, ExactView),
+    Fix2x61(Set<Fix2x61>),
+    DoubleDouble(Set<DoubleDouble>),
+    FixN2(Set<FixN<2>>),
+    FixN4(Set<FixN<4>>),
+    FixN8(Set<FixN<8>>),
+    FixN16(Set<FixN<16>>),
+    FixV(Set<FixV>),
+}
+
+// The view an f64 set is showing, as it was given to us rather than as f64 rounds it.
+#[derive(Clone, Copy, Debug)]
+pub struct ExactView {
+    centre: Complex<Fix2x61>,
+    radius: Fix2x61,
+    julia: Option<Complex<Fix2x61>>,
+}
+
+macro_rules! each_set {
+    ($self:expr, $set:ident => $body:expr) => {
+        match $self {
+            PromotingSet::F64($set, _) => $body,
+            PromotingSet::Fix2x61($set) => $body,
+            PromotingSet::DoubleDouble($set) => $body,
+            PromotingSet::FixN2($set) => $body,
+            PromotingSet::FixN4($set) => $body,
+            PromotingSet::FixN8($set) => $body,
+            PromotingSet::FixN16($set) => $body,
+            PromotingSet::FixV($set) => $body,
+        }
+    };
+}
+
+impl PromotingSet {
+    pub fn create(
+        power_size: usize,
+        centre: Complex<Fix2x61>,
+        radius: Fix2x61,
+    ) -> Result<PromotingSet, Error> {
+        PromotingSet::create_with(power_size, centre, radius, None)
+    }
+
+    pub fn create_julia(
+        power_size: usize,
+        centre: Complex<Fix2x61>,
+        radius: Fix2x61,
+        c: Complex<Fix2x61>,
+    ) -> Result<PromotingSet, Error> {
+        PromotingSet::create_with(power_size, centre, radius, Some(c))
+    }
+
+    fn create_with(
+        power_size: usize,
+        centre: Complex<Fix2x61>,
+        radius: Fix2x61,
+        julia: Option<Complex<Fix2x61>>,
+    ) -> Result<PromotingSet, Error> {
+        let to_f64 = |c: Complex<Fix2x61>| Complex::new(c.r.to_f64(), c.i.to_f64());
+        let fast = match julia {
+            Some(c) => Set::create_julia(power_size, to_f64(centre), radius.to_f64(), to_f64(c))?,
+            None => Set::create(power_size, to_f64(centre), radius.to_f64())?,
+        };
+        if fast.needs_promotion() {
+            Ok(PromotingSet::Fix2x61(match julia {
+                Some(c) => Set::create_julia(power_size, centre, radius, c)?,
+                None => Set::create(power_size, centre, radius)?,
+            }))
+        } else {
+            Ok(PromotingSet::F64(
+                fast,
+                ExactView {
+                    centre,
+                    radius,
+                    julia,
+                },
+            ))
+        }
+    }
+
+    pub fn subset(&self, quad: &Quad) -> Result<PromotingSet, Error> {
+        let promoted = match self {
+            PromotingSet::F64(set, exact) if set.subset_needs_promotion() => {
+                let mut promoted: Set<Fix2x61> = set.promote()?;
+                promoted.centre = exact.centre;
+                promoted.radius = exact.radius;
+                promoted.julia = exact.julia;
+                PromotingSet::Fix2x61(promoted)
+            }
+            PromotingSet::Fix2x61(set) if set.subset_needs_promotion() => {
+                PromotingSet::DoubleDouble(set.promote()?)
+            }
+            PromotingSet::DoubleDouble(set) if set.subset_needs_promotion() => {
+                PromotingSet::FixN2(set.promote()?)
+            }
+            PromotingSet::FixN2(set) if set.subset_needs_promotion() => {
+                PromotingSet::FixN4(set.promote()?)
+            }
+            PromotingSet::FixN4(set) if set.subset_needs_promotion() => {
+                PromotingSet::FixN8(set.promote()?)
+            }
+            PromotingSet::FixN8(set) if set.subset_needs_promotion() => {
+                PromotingSet::FixN16(set.promote()?)
+            }
+            PromotingSet::FixN16(set) if set.subset_needs_promotion() => PromotingSet::FixV(
+                set.promote_with(|n| Ok::<_, FixError>(FixV::from(*n).extend(32)))?,
+            ),
+            PromotingSet::FixV(set) if set.subset_needs_promotion() => {
+                let limbs = 2 * set.radius.limbs();
+                PromotingSet::FixV(set.promote_with(|n| Ok::<_, FixError>(n.extend(limbs)))?)
+            }
+            _ => return self.subset_in_place(quad),
+        };
+        promoted.subset_in_place(quad)
+    }
+
+    fn subset_in_place(&self, quad: &Quad) -> Result<PromotingSet, Error> {
+        Ok(match self {
+            PromotingSet::F64(set, exact) => {
+                let stored = set.orientation.stored_quad(quad);
+                let (centre, radius) = quad_view(exact.centre, exact.radius, &stored)?;
+                let exact = ExactView {
+                    centre,
+                    radius,
+                    ..*exact
+                };
+                PromotingSet::F64(set.subset(quad)?, exact)
+            }
+            PromotingSet::Fix2x61(set) => PromotingSet::Fix2x61(set.subset(quad)?),
+            PromotingSet::DoubleDouble(set) => PromotingSet::DoubleDouble(set.subset(quad)?),
+            PromotingSet::FixN2(set) => PromotingSet::FixN2(set.subset(quad)?),
+            PromotingSet::FixN4(set) => PromotingSet::FixN4(set.subset(quad)?),
+            PromotingSet::FixN8(set) => PromotingSet::FixN8(set.subset(quad)?),
+            PromotingSet::FixN16(set) => PromotingSet::FixN16(set.subset(quad)?),
+            PromotingSet::FixV(set) => PromotingSet::FixV(set.subset(quad)?),
+        })
+    }
+
+    pub fn number_type(&self) -> &'static str {
+        each_set!(self, set => type_name_of(set))
+    }
+
+    pub fn seen_escapes_to(&self) -> u64 {
+        each_set!(self, set => set.seen_escapes_to())
+    }
+
+    pub fn iterate_as_required(&mut self, min_iter: u64, verbose: bool) -> Result<(), Error> {
+        each_set!(self, set => set.iterate_as_required(min_iter, verbose))
+    }
+
+    pub fn set_series_tolerance(&mut self, tolerance: Option<f64>) {
+        each_set!(self, set => set.set_series_tolerance(tolerance))
+    }
+
+    pub fn set_formula(&mut self, formula: Formula) {
+        each_set!(self, set => set.set_formula(formula))
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        each_set!(self, set => set.set_orientation(orientation))
+    }
+
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        each_set!(self, set => set.set_strategy(strategy))
+    }
+
+    pub fn set_seeding(&mut self, seeding: Seeding) {
+        each_set!(self, set => set.set_seeding(seeding))
+    }
+
+    pub fn estimate_distances(&mut self) {
+        each_set!(self, set => set.estimate_distances())
+    }
+
+    pub fn iterate_perturbed(&mut self, max_iter: u64, verbose: bool) -> Result<(), Error> {
+        each_set!(self, set => set.iterate_perturbed(max_iter, verbose))
+    }
+
+    pub fn size(&self) -> u32 {
+        each_set!(self, set => set.size())
+    }
+
+    pub fn render_to_file(&self, scheme: &ColourScheme, filename: &str) -> std::io::Result<()> {
+        each_set!(self, set => set.render_to_file(scheme, filename))
+    }
+}
+
+fn type_name_of<N>(_: &Set<N>) -> &'static str {
+    type_name::<N>()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::fix::fixn::FixN;
+    use crate::fix::fixv::FixV;
+    use crate::set::{Orientation, Set};
+    use crate::zoom_path::Quad;
+
+    use super::PromotingSet;
+
+    #[test]
+    fn zooms_past_fix2x61() -> Result<(), Error> {
+        let mut set = PromotingSet::create(2, Complex::zero(), Fix2x61::two())?;
+        for _ in 0..60 {
+            set = set.subset(&Quad::TopLeft)?;
+        }
+        assert!(matches!(set, PromotingSet::DoubleDouble(_)));
+        for _ in 0..90 {
+            set = set.subset(&Quad::TopLeft)?;
+        }
+        assert!(matches!(set, PromotingSet::FixN4(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn zooms_past_fixn16() -> Result<(), Error> {
+        let mut set = PromotingSet::create(2, Complex::zero(), Fix2x61::two())?;
+        for _ in 0..1008 {
+            set = set.subset(&Quad::BottomRight)?;
+        }
+        assert!(matches!(set, PromotingSet::FixN16(_)));
+        set = set.subset(&Quad::BottomRight)?;
+        match &set {
+            PromotingSet::FixV(set) => assert_eq!(set.radius.limbs(), 32),
+            _ => panic!("Expected a FixV set"),
+        }
+        for _ in 0..2040 {
+            set = set.subset(&Quad::BottomRight)?;
+        }
+        match &set {
+            PromotingSet::FixV(set) => assert_eq!(set.radius.limbs(), 64),
+            _ => panic!("Expected a FixV set"),
+        }
+        set.iterate_as_required(10, false)?;
+        Ok(())
+    }
+
+    #[test]
+    fn fixv_keeps_the_picture() -> Result<(), Error> {
+        let mut fixed = PromotingSet::FixN16(Set::create(5, Complex::zero(), FixN::two())?);
+        let mut wide = PromotingSet::FixV(Set::create(5, Complex::zero(), FixV::TWO.extend(32))?);
+        for quad in [Quad::TopLeft, Quad::BottomRight, Quad::BottomLeft].iter() {
+            fixed = fixed.subset(quad)?;
+            wide = wide.subset(quad)?;
+        }
+        fixed.iterate_as_required(200, false)?;
+        wide.iterate_as_required(200, false)?;
+        assert_eq!(fixed.seen_escapes_to(), wide.seen_escapes_to());
+        Ok(())
+    }
+
+    #[test]
+    fn stays_narrow_when_shallow() -> Result<(), Error> {
+        let mut set = PromotingSet::create(4, Complex::zero(), Fix2x61::two())?;
+        for _ in 0..10 {
+            set = set.subset(&Quad::BottomRight)?;
+        }
+        assert!(matches!(set, PromotingSet::F64(..)));
+        Ok(())
+    }
+
+    #[test]
+    fn falls_back_to_fixed_point() -> Result<(), Error> {
+        let mut set = PromotingSet::create(4, Complex::zero(), Fix2x61::two())?;
+        for _ in 0..40 {
+            set = set.subset(&Quad::TopRight)?;
+        }
+        assert!(matches!(set, PromotingSet::Fix2x61(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn starts_in_fixed_point_when_already_deep() -> Result<(), Error> {
+        let radius = Fix2x61::power_of_two(-45)?;
+        let set = PromotingSet::create(8, Complex::zero(), radius)?;
+        assert!(matches!(set, PromotingSet::Fix2x61(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn promotion_keeps_the_picture() -> Result<(), Error> {
+        let mut fast = PromotingSet::create(5, Complex::zero(), Fix2x61::two())?;
+        let mut fixed = PromotingSet::Fix2x61(Set::create(5, Complex::zero(), Fix2x61::two())?);
+        for quad in [Quad::TopLeft, Quad::BottomRight, Quad::BottomLeft].iter() {
+            fast = fast.subset(quad)?;
+            fixed = fixed.subset(quad)?;
+        }
+        fast.iterate_as_required(200, false)?;
+        fixed.iterate_as_required(200, false)?;
+        assert_eq!(fast.seen_escapes_to(), fixed.seen_escapes_to());
+        Ok(())
+    }
+
+    #[test]
+    fn julia_sets_zoom_and_promote() -> Result<(), Error> {
+        // The Julia set for zero is the unit disc.
+        let mut set =
+            PromotingSet::create_julia(4, Complex::zero(), Fix2x61::two(), Complex::zero())?;
+        set.iterate_as_required(500, false)?;
+        match &set {
+            PromotingSet::F64(set, _) => {
+                let size = set.size() as usize;
+                for y in 0..size - 1 {
+                    for x in 0..size - 1 {
+                        let (r, i) = (-1.875 + 0.25 * x as f64, -1.875 + 0.25 * y as f64);
+                        let escaped = set.points[x + size * y].escaped;
+                        assert_eq!(escaped, r * r + i * i > 1.0, "{}, {}", r, i);
+                    }
+                }
+            }
+            _ => panic!("Expected an f64 set"),
+        }
+
+        for _ in 0..40 {
+            set = set.subset(&Quad::TopRight)?;
+        }
+        match &set {
+            PromotingSet::Fix2x61(set) => assert_eq!(set.julia, Some(Complex::zero())),
+            _ => panic!("Expected a Fix2x61 set"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn julia_parameters_keep_their_precision() -> Result<(), Error> {
+        // Further from -0.8 than f64 can see.
+        let r = Fix2x61::try_from(-0.8)?.try_add(Fix2x61::power_of_two(-60)?)?;
+        let c = Complex::new(r, Fix2x61::try_from(0.156)?);
+        let mut set = PromotingSet::create_julia(4, Complex::zero(), Fix2x61::two(), c)?;
+        for _ in 0..40 {
+            set = set.subset(&Quad::TopRight)?;
+        }
+        match &set {
+            PromotingSet::Fix2x61(set) => assert_eq!(set.julia, Some(c)),
+            _ => panic!("Expected a Fix2x61 set"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn centres_keep_their_precision() -> Result<(), Error> {
+        // Further from -0.75 than f64 can see, and deep enough for that to be many view radii.
+        let r = Fix2x61::try_from(-0.75)?.try_add(Fix2x61::power_of_two(-60)?)?;
+        let centre = Complex::new(r, Fix2x61::try_from(0.125)?);
+        let mut fast = PromotingSet::create(4, centre, Fix2x61::two())?;
+        let mut fixed = Set::create(4, centre, Fix2x61::two())?;
+        assert!(matches!(fast, PromotingSet::F64(..)));
+        for quad in [Quad::TopRight, Quad::BottomLeft].iter().cycle().take(44) {
+            fast = fast.subset(quad)?;
+            fixed = fixed.subset(quad)?;
+        }
+        match &fast {
+            PromotingSet::Fix2x61(set) => assert_eq!(set.centre, fixed.centre),
+            _ => panic!("Expected a Fix2x61 set"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn zoom_paths_follow_the_picture() -> Result<(), Error> {
+        let mut set: Set = Set::create(4, Complex::zero(), Fix2x61::two())?;
+        let one = Fix2x61::one();
+        assert_eq!(set.subset(&Quad::TopLeft)?.centre, Complex::new(-one, -one));
+
+        set.set_orientation(Orientation::ImaginaryUp);
+        let subset = set.subset(&Quad::TopLeft)?;
+        assert_eq!(subset.centre, Complex::new(-one, one));
+        assert_eq!(subset.orientation, Orientation::ImaginaryUp);
+        Ok(())
+    }
+}
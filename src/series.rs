@@ -0,0 +1,222 @@
+use anyhow::{bail, Context, Error};
+use rayon::prelude::*;
+
+use crate::complex::{Complex, FixError};
+use crate::number::MandelbrotNumber;
+use crate::set::Set;
+
+// Where we check the series against real perturbed iteration, as multiples of the radius away
+// from the centre: the corners and edges of the view.
+const PROBES: [(f64, f64); 8] = [
+    (-1.0, -1.0),
+    (1.0, -1.0),
+    (-1.0, 1.0),
+    (1.0, 1.0),
+    (0.0, -1.0),
+    (0.0, 1.0),
+    (-1.0, 0.0),
+    (1.0, 0.0),
+];
+
+// The offset of a point from the reference after `iterations` iterations, as a cubic in that
+// point's initial offset: dz = A dc + B dc^2 + C dc^3.  Each coefficient is scaled by the matching
+// power of the set's radius, so the polynomial is evaluated at dc / radius and the coefficients
+// stay within f64's range however deep we zoom.
+#[derive(Copy, Clone, Debug)]
+pub struct SeriesApproximation {
+    pub a: Complex<f64>,
+    pub b: Complex<f64>,
+    pub c: Complex<f64>,
+    pub iterations: u64,
+}
+
+impl SeriesApproximation {
+    fn start(radius: f64) -> Self {
+        SeriesApproximation {
+            a: Complex::new(radius, 0.0),
+            b: Complex::zero(),
+            c: Complex::zero(),
+            iterations: 0,
+        }
+    }
+
+    // One more iteration along a reference orbit currently at z.
+    fn step(&self, z: Complex<f64>, radius: f64) -> Self {
+        let two_z = scale(z, 2.0);
+        SeriesApproximation {
+            a: add(mul(two_z, self.a), Complex::new(radius, 0.0)),
+            b: add(mul(two_z, self.b), mul(self.a, self.a)),
+            c: add(mul(two_z, self.c), scale(mul(self.a, self.b), 2.0)),
+            iterations: self.iterations + 1,
+        }
+    }
+
+    pub fn evaluate(&self, u: Complex<f64>) -> Complex<f64> {
+        let u2 = mul(u, u);
+        add(
+            add(mul(self.a, u), mul(self.b, u2)),
+            mul(self.c, mul(u2, u)),
+        )
+    }
+}
+
+fn add(a: Complex<f64>, b: Complex<f64>) -> Complex<f64> {
+    Complex::new(a.r + b.r, a.i + b.i)
+}
+
+fn mul(a: Complex<f64>, b: Complex<f64>) -> Complex<f64> {
+    Complex::new(a.r * b.r - a.i * b.i, a.r * b.i + a.i * b.r)
+}
+
+fn scale(a: Complex<f64>, s: f64) -> Complex<f64> {
+    Complex::new(a.r * s, a.i * s)
+}
+
+fn norm(a: Complex<f64>) -> f64 {
+    a.r.hypot(a.i)
+}
+
+impl<N: MandelbrotNumber> Set<N> {
+    // Finds how many iterations a cubic series around the centre of the set can stand in for,
+    // checking it against perturbed iteration of probe points around the edge of the view, then
+    // moves every point straight to that iteration.  Each probe's series value must be within
+    // `tolerance` (relative) of its real value, and the cubic term must stay that much smaller
+    // than the quadratic one.
+    pub fn skip_with_series(
+        &mut self,
+        tolerance: f64,
+        max_skip: u64,
+        verbose: bool,
+    ) -> Result<u64, Error> {
+        if !self.is_mandelbrot() {
+            bail!("Series approximation only supports the Mandelbrot set");
+        }
+        let radius = self.radius.to_f64();
+        let reference = self.centre.clone();
+        let dcs: Vec<Complex<f64>> = PROBES
+            .iter()
+            .map(|&(r, i)| Complex::new(r * radius, i * radius))
+            .collect();
+        let mut deltas = dcs.clone();
+        let mut series = SeriesApproximation::start(radius);
+        let mut z = reference.clone();
+
+        while series.iterations < max_skip {
+            let z_f64 = Complex::new(z.r.to_f64(), z.i.to_f64());
+            let mut next_z = z.clone();
+            match next_z.iterate_mandelbrot(&reference) {
+                Ok(()) => {}
+                Err(FixError::Escaped) => break,
+                Err(e) => return Err(e).context("Iterating series reference"),
+            }
+            let next_z_f64 = Complex::new(next_z.r.to_f64(), next_z.i.to_f64());
+            let next_series = series.step(z_f64, radius);
+
+            let next_deltas: Vec<Complex<f64>> = deltas
+                .iter()
+                .zip(dcs.iter())
+                .map(|(&d, &dc)| add(add(mul(scale(z_f64, 2.0), d), mul(d, d)), dc))
+                .collect();
+            let probes_agree = next_deltas.iter().zip(PROBES.iter()).all(|(&d, &(r, i))| {
+                let escaped = norm(add(next_z_f64, d)) >= 2.0;
+                let error = norm(add(
+                    next_series.evaluate(Complex::new(r, i)),
+                    scale(d, -1.0),
+                ));
+                !escaped && error <= tolerance * norm(d)
+            });
+            if !probes_agree || norm(next_series.c) > tolerance * norm(next_series.b) {
+                break;
+            }
+
+            z = next_z;
+            series = next_series;
+            deltas = next_deltas;
+        }
+
+        if series.iterations > 0 {
+            self.points
+                .par_iter_mut()
+                .try_for_each(|p| -> Result<(), Error> {
+                    let loc = p.loc();
+                    let u = Complex::new(
+                        loc.r.clone().try_sub(reference.r.clone())?.to_f64() / radius,
+                        loc.i.clone().try_sub(reference.i.clone())?.to_f64() / radius,
+                    );
+                    let d = series.evaluate(u);
+                    let value = (
+                        N::from_f64(d.r).and_then(|d| z.r.clone().try_add(d)),
+                        N::from_f64(d.i).and_then(|d| z.i.clone().try_add(d)),
+                    );
+                    // Anything that's escaped already, or that's so far from the centre the series is
+                    // meaningless, gets iterated from the start as usual.
+                    if let (Ok(r), Ok(i)) = value {
+                        if norm(Complex::new(r.to_f64(), i.to_f64())) < 2.0 {
+                            p.skip_to(Complex::new(r, i), series.iterations);
+                        }
+                    }
+                    Ok(())
+                })?;
+        }
+
+        if verbose {
+            println!(
+                "Series approximation skipped {} iterations",
+                series.iterations
+            );
+        }
+        Ok(series.iterations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::fix::fixn::FixN;
+    use crate::set::Set;
+
+    fn cusp_set(tolerance: Option<f64>) -> Result<Set<FixN<2>>, Error> {
+        let cusp = (FixN::<2>::try_from(0.25)? + FixN::power_of_two(-16)?)?;
+        let mut set = Set::create(
+            4,
+            Complex::new(cusp, FixN::zero()),
+            FixN::power_of_two(-60)?,
+        )?;
+        set.set_series_tolerance(tolerance);
+        Ok(set)
+    }
+
+    #[test]
+    fn skips_iterations_deep_down() -> Result<(), Error> {
+        let mut set = cusp_set(None)?;
+        let skipped = set.skip_with_series(1e-9, 2000, false)?;
+        assert!(skipped > 100, "skipped {}", skipped);
+        assert!(set.points.iter().any(|p| p.iterations == skipped));
+        Ok(())
+    }
+
+    #[test]
+    fn skipping_does_not_change_the_picture() -> Result<(), Error> {
+        let mut skipped = cusp_set(Some(1e-9))?;
+        let mut iterated = cusp_set(None)?;
+        skipped.iterate_as_required(2000, false)?;
+        iterated.iterate_as_required(2000, false)?;
+        for (s, i) in skipped.points.iter().zip(iterated.points.iter()) {
+            assert_eq!(s.escaped, i.escaped);
+            assert!((s.iterations as i64 - i.iterations as i64).abs() <= 1);
+        }
+        assert!(skipped.points.iter().any(|p| p.escaped));
+        Ok(())
+    }
+
+    #[test]
+    fn skips_little_in_a_wide_view() -> Result<(), Error> {
+        let mut set: Set<f64> = Set::create(5, Complex::zero(), 2.0)?;
+        assert!(set.skip_with_series(1e-9, 500, false)? < 3);
+        Ok(())
+    }
+}
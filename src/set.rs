@@ -1,33 +1,210 @@
 use std::mem::size_of_val;
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use itertools::Itertools;
 use rayon::prelude::*;
 
-use crate::complex::Complex;
+use crate::buddhabrot::{split_mix, unit};
+use crate::complex::{Complex, FixResult};
 use crate::fix::fix2x61::Fix2x61;
+use crate::formula::Formula;
+use crate::number::{MandelbrotNumber, WideNumber};
 use crate::point::Point;
 use crate::zoom_path::Quad;
 use std::cmp::min;
+use std::convert::TryFrom;
+use std::str::FromStr;
 
-pub struct Set {
-    pub(crate) points: Vec<Point>,
-    power_size: usize,
-    centre: Complex,
-    radius: Fix2x61,
+// How many bits of precision we insist on keeping below the spacing between points, to absorb the
+// rounding errors that accumulate while iterating.
+const GUARD_BITS: i32 = 12;
+
+// The period of the cycle that c is drawn towards, for the points of the Mandelbrot set we can
+// recognise without iterating: those in the main cardioid, with period one, and those in the
+// period two bulb.  Products are exact in the wide type, and the few truncations are covered by
+// a margin of the guard bits, so points too close to the edge to be sure of are left to be
+// iterated.  Anything far enough out to overflow is nowhere near either.
+pub(crate) fn known_period<N: MandelbrotNumber>(c: &Complex<N>) -> Option<u64> {
+    let margin = N::power_of_two(GUARD_BITS - c.r.precision_bits() as i32)
+        .ok()?
+        .widen();
+    let quarter = N::power_of_two(-2).ok()?;
+    let i_squared = c.i.clone().widening_square();
+
+    // With x = r - 1/4 and q = x^2 + i^2, inside the cardioid q (q + x) < i^2 / 4.
+    let cardioid = || -> FixResult<N::Wide> {
+        let x = c.r.clone().try_sub(quarter.clone())?;
+        let q = x
+            .clone()
+            .widening_square()
+            .try_add(i_squared.clone())?
+            .truncate()?;
+        let bound = quarter.clone().widening_mul(i_squared.truncate()?);
+        q.clone()
+            .widening_mul(q.try_add(x)?)
+            .try_add(margin.clone())?
+            .try_sub(bound)
+    };
+    // A disc of radius 1/4 around -1.
+    let bulb = || -> FixResult<N::Wide> {
+        c.r.clone()
+            .try_add(N::ONE)?
+            .widening_square()
+            .try_add(i_squared.clone())?
+            .try_add(margin.clone())?
+            .try_sub(quarter.clone().widening_square())
+    };
+    let inside = |difference: FixResult<N::Wide>| difference.is_ok_and(|d| d.is_negative());
+    if inside(cardioid()) {
+        Some(1)
+    } else if inside(bulb()) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+// Which way up the imaginary axis goes when the set is drawn.  Points are always stored with the
+// smallest imaginary part first, which is the top of the picture by default.  Burning Ship is
+// conventionally drawn that way too, which is what makes the ship upright.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Orientation {
+    #[default]
+    ImaginaryDown,
+    ImaginaryUp,
+}
+
+impl Orientation {
+    // The quad as the points are stored, given the quad as it appears in the picture.
+    pub(crate) fn stored_quad(&self, quad: &Quad) -> Quad {
+        match self {
+            Orientation::ImaginaryDown => *quad,
+            Orientation::ImaginaryUp => quad.flip_vertical(),
+        }
+    }
+}
+
+// The centre and radius of one quad of the view with the given centre and radius, with the quad
+// as the points are stored.
+pub(crate) fn quad_view<N: MandelbrotNumber>(
+    centre: Complex<N>,
+    radius: N,
+    quad: &Quad,
+) -> FixResult<(Complex<N>, N)> {
+    let radius = radius.halve()?;
+    let (r, i) = (centre.r, centre.i);
+    let centre = match quad {
+        Quad::TopLeft => Complex::new(r.try_sub(radius.clone())?, i.try_sub(radius.clone())?),
+        Quad::TopRight => Complex::new(r.try_add(radius.clone())?, i.try_sub(radius.clone())?),
+        Quad::BottomLeft => Complex::new(r.try_sub(radius.clone())?, i.try_add(radius.clone())?),
+        Quad::BottomRight => Complex::new(r.try_add(radius.clone())?, i.try_add(radius.clone())?),
+    };
+    Ok((centre, radius))
+}
+
+// How iterate_as_required decides which points to iterate.  Flooding starts from the edge of the
+// picture and spreads to the neighbours of every point that escapes.  Mariani-Silver iterates the
+// border of a rectangle and fills it in without iterating the rest if the whole border is the
+// same, or splits it in four if not.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Strategy {
+    #[default]
+    Flood,
+    MarianiSilver,
+}
+
+impl FromStr for Strategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "flood" => Strategy::Flood,
+            "mariani-silver" => Strategy::MarianiSilver,
+            _ => bail!(
+                "'{}' is not a known strategy: try flood or mariani-silver",
+                s
+            ),
+        })
+    }
+}
+
+// Where flooding starts from, besides the edge of a newly created picture.  Escaping regions that
+// don't reach the edge through other escaping points are only found if one of these probes lands
+// in them: a grid of every eighth point in each direction, as many different points again picked
+// at random, or every point.  Random probes come from the seed, which each subset mixes its quad
+// into, so deeper views don't probe the same places as the ones above them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Seeding {
+    #[default]
+    Border,
+    Grid,
+    Random(u64),
+    Full,
 }
 
-impl Set {
-    pub fn subset(&self, quad: &Quad) -> Result<Set, Error> {
+impl Seeding {
+    fn for_subset(self, quad: &Quad) -> Seeding {
+        match self {
+            Seeding::Random(seed) => Seeding::Random(split_mix(seed ^ (*quad as u64 + 1))),
+            seeding => seeding,
+        }
+    }
+}
+
+impl FromStr for Seeding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.split_once(':') {
+            Some(("random", seed)) => Seeding::Random(
+                seed.parse()
+                    .with_context(|| format!("'{}' is not a valid seed", seed))?,
+            ),
+            _ => match s {
+                "border" => Seeding::Border,
+                "grid" => Seeding::Grid,
+                "random" => Seeding::Random(0),
+                "full" => Seeding::Full,
+                _ => bail!(
+                    "'{}' is not a known seeding: try border, grid, random, random:<seed> or full",
+                    s
+                ),
+            },
+        })
+    }
+}
+
+const PROBE_SPACING: usize = 8;
+
+pub struct Set<N = Fix2x61> {
+    pub(crate) points: Vec<Point<N>>,
+    pub(crate) power_size: usize,
+    pub(crate) centre: Complex<N>,
+    pub(crate) radius: N,
+    series_tolerance: Option<f64>,
+    // The parameter for a Julia set, or None for the Mandelbrot set.
+    pub(crate) julia: Option<Complex<N>>,
+    pub(crate) formula: Formula,
+    pub(crate) orientation: Orientation,
+    strategy: Strategy,
+    seeding: Seeding,
+    pub(crate) distances: bool,
+}
+
+impl<N: MandelbrotNumber> Set<N> {
+    // Quads are as they appear in the picture, so with the imaginary axis up they're flipped
+    // relative to how the points are stored.
+    pub fn subset(&self, quad: &Quad) -> Result<Set<N>, Error> {
+        let quad = &self.orientation.stored_quad(quad);
         let power_size = self.power_size;
-        let radius = self.radius.halve()?;
-        let centre = match quad {
-            Quad::TopLeft => Complex::new((self.centre.r - radius)?, (self.centre.i - radius)?),
-            Quad::TopRight => Complex::new((self.centre.r + radius)?, (self.centre.i - radius)?),
-            Quad::BottomLeft => Complex::new((self.centre.r - radius)?, (self.centre.i + radius)?),
-            Quad::BottomRight => Complex::new((self.centre.r + radius)?, (self.centre.i + radius)?),
-        };
-        let mut points = Set::generate_points(power_size, centre, radius)?;
+        let (centre, radius) = quad_view(self.centre.clone(), self.radius.clone(), quad)?;
+        let mut points = Set::generate_points(
+            power_size,
+            centre.clone(),
+            radius.clone(),
+            self.julia.clone(),
+            self.formula,
+        )?;
 
         let size = 1usize << power_size;
         let half_size = 1usize << (power_size - 1);
@@ -40,29 +217,120 @@ impl Set {
         for x in 0..half_size {
             for y in 0..half_size {
                 let old_points_i = start + x + size * y;
-                if self.points[old_points_i].escaped {
-                    let base = (x * 2 + size * y * 2) as i64;
-                    update_if_in_range(&mut points, base, size as i64);
-                    update_if_in_range(&mut points, base + 1, size as i64);
-                    update_if_in_range(&mut points, base + size as i64, size as i64);
-                    update_if_in_range(&mut points, base + size as i64 + 1, size as i64);
+                if self.points[old_points_i].finished() {
+                    for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                        points[x * 2 + dx + size * (y * 2 + dy)].escape_candidate = true;
+                    }
                 }
             }
         }
 
-        Ok(Set {
+        let mut subset = Set {
             points,
             power_size,
             centre,
             radius,
-        })
+            series_tolerance: self.series_tolerance,
+            julia: self.julia.clone(),
+            formula: self.formula,
+            orientation: self.orientation,
+            strategy: self.strategy,
+            seeding: self.seeding.for_subset(quad),
+            distances: false,
+        };
+        if self.distances {
+            subset.estimate_distances();
+        }
+        Ok(subset)
+    }
+
+    // True if this set's points are so close together that N can no longer tell them apart
+    // reliably.
+    pub fn needs_promotion(&self) -> bool {
+        self.needs_promotion_after(0)
+    }
+
+    // True if a subset of this set would need a wider number type.
+    pub fn subset_needs_promotion(&self) -> bool {
+        self.needs_promotion_after(1)
+    }
+
+    fn needs_promotion_after(&self, levels: i32) -> bool {
+        let spacing = self
+            .radius
+            .exponent()
+            .map_or(i32::MIN, |e| e - levels - self.power_size as i32);
+        spacing < GUARD_BITS - self.radius.precision_bits() as i32
+    }
+
+    // Re-creates this set's points, and their progress so far, in a wider number type.
+    pub fn promote<M>(&self) -> Result<Set<M>, Error>
+    where
+        M: MandelbrotNumber + TryFrom<N>,
+        M::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.promote_with(|n| M::try_from(n.clone()))
+    }
+
+    // The same, with `number` converting each number.
+    pub fn promote_with<M, E>(&self, number: impl Fn(&N) -> Result<M, E>) -> Result<Set<M>, Error>
+    where
+        M: MandelbrotNumber,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let points: Result<Vec<Point<M>>, _> =
+            self.points.iter().map(|p| p.convert(&number)).collect();
+        let mut set = Set {
+            points: points?,
+            power_size: self.power_size,
+            centre: self.centre.convert_with(&number)?,
+            radius: number(&self.radius)?,
+            series_tolerance: self.series_tolerance,
+            julia: self
+                .julia
+                .as_ref()
+                .map(|c| c.convert_with(&number))
+                .transpose()?,
+            formula: self.formula,
+            orientation: self.orientation,
+            strategy: self.strategy,
+            seeding: self.seeding,
+            distances: self.distances,
+        };
+        set.mark_known_interior();
+        Ok(set)
     }
 }
 
-impl Set {
-    pub fn create(power_size: usize, centre: Complex, radius: Fix2x61) -> Result<Set, Error> {
+impl<N: MandelbrotNumber> Set<N> {
+    pub fn create(power_size: usize, centre: Complex<N>, radius: N) -> Result<Set<N>, Error> {
+        Set::create_with(power_size, centre, radius, None)
+    }
+
+    // The Julia set for c, over the same kind of view as create gives of the Mandelbrot set.
+    pub fn create_julia(
+        power_size: usize,
+        centre: Complex<N>,
+        radius: N,
+        c: Complex<N>,
+    ) -> Result<Set<N>, Error> {
+        Set::create_with(power_size, centre, radius, Some(c))
+    }
+
+    fn create_with(
+        power_size: usize,
+        centre: Complex<N>,
+        radius: N,
+        julia: Option<Complex<N>>,
+    ) -> Result<Set<N>, Error> {
         //println!("Starting to allocate");
-        let mut points = Set::generate_points(power_size, centre, radius)?;
+        let mut points = Set::generate_points(
+            power_size,
+            centre.clone(),
+            radius.clone(),
+            julia.clone(),
+            Formula::MANDELBROT,
+        )?;
 
         let size = 1 << power_size;
         for (i, p) in points.iter_mut().enumerate() {
@@ -76,35 +344,224 @@ impl Set {
             power_size,
             centre,
             radius,
+            series_tolerance: None,
+            julia,
+            formula: Formula::MANDELBROT,
+            orientation: Orientation::default(),
+            strategy: Strategy::default(),
+            seeding: Seeding::default(),
+            distances: false,
         })
     }
 
+    // Switches to a different formula.  This only makes sense before iterating.
+    pub fn set_formula(&mut self, formula: Formula) {
+        self.formula = formula;
+        let julia = self.julia.is_some();
+        for p in self.points.iter_mut() {
+            p.formula = formula;
+            if !julia {
+                p.restart();
+            }
+        }
+        self.mark_known_interior();
+    }
+
+    // Points that haven't been iterated yet lose any cycle they were known to be drawn towards
+    // when they change formula or number type, so we check them again.
+    fn mark_known_interior(&mut self) {
+        let mandelbrot = self.is_mandelbrot();
+        for p in self.points.iter_mut().filter(|p| p.iterations == 0) {
+            p.period = if mandelbrot {
+                known_period(p.loc())
+            } else {
+                None
+            };
+        }
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.strategy = strategy;
+    }
+
+    // Follows the derivative of every point that hasn't been iterated yet, here and in subsets, so
+    // that points of the Multibrot sets have a distance estimate once they escape.  Points whose
+    // early iterations the series approximation skips don't get one.
+    pub fn estimate_distances(&mut self) {
+        self.distances = true;
+        let julia = self.julia.is_some();
+        for p in self.points.iter_mut().filter(|p| p.iterations == 0) {
+            p.track_derivative(julia);
+        }
+    }
+
+    // Only flooding uses this.
+    pub fn set_seeding(&mut self, seeding: Seeding) {
+        self.seeding = seeding;
+    }
+
+    fn seed(&mut self) {
+        for i in self.probes() {
+            self.points[i].escape_candidate = true;
+        }
+    }
+
+    fn probes(&self) -> Vec<usize> {
+        let size = 1 << self.power_size;
+        match self.seeding {
+            Seeding::Border => vec![],
+            Seeding::Grid => {
+                let line = || (PROBE_SPACING / 2..size).step_by(PROBE_SPACING);
+                line()
+                    .flat_map(|y| line().map(move |x| x + size * y))
+                    .collect()
+            }
+            Seeding::Random(seed) => {
+                // The start of a partial Fisher-Yates shuffle, so no point is picked twice.
+                let stream = split_mix(seed);
+                let mut indices: Vec<usize> = (0..size * size).collect();
+                let count = size * size / (PROBE_SPACING * PROBE_SPACING);
+                for k in 0..count {
+                    let left = (size * size - k) as f64;
+                    let pick = k + (unit(stream.wrapping_add(k as u64)) * left) as usize;
+                    indices.swap(k, pick);
+                }
+                indices.truncate(count);
+                indices
+            }
+            Seeding::Full => (0..size * size).collect(),
+        }
+    }
+
+    // Groups of escaped points, joined up through their neighbours, that don't reach the edge of
+    // the picture.  Flooding in from the edge only finds these when points that settle on an
+    // attractor lead it there.
+    pub fn islands(&self) -> usize {
+        self.count_islands(&vec![true; self.points.len()])
+    }
+
+    // How many of the islands flooding in from the edge alone would have found.  More seeds only
+    // ever flood further, so every point it would have reached has been iterated, and finished or
+    // not just as it would have been then.
+    pub fn islands_from_border(&self) -> usize {
+        let size = 1 << self.power_size;
+        let mut reached = vec![false; size * size];
+        let mut stack: Vec<usize> = (0..size * size)
+            .filter(|i| {
+                let (x, y) = (i % size, i / size);
+                x == 0 || y == 0 || x == size - 1 || y == size - 1
+            })
+            .collect();
+        for &i in stack.iter() {
+            reached[i] = true;
+        }
+        while let Some(i) = stack.pop() {
+            if !self.points[i].finished() {
+                continue;
+            }
+            for (nx, ny) in neighbours(i % size, i / size, size) {
+                let j = nx + size * ny;
+                if !reached[j] {
+                    reached[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+        self.count_islands(&reached)
+    }
+
+    // Islands with at least one point in `found`.
+    fn count_islands(&self, found: &[bool]) -> usize {
+        let size = 1 << self.power_size;
+        let mut seen = vec![false; size * size];
+        let mut stack = vec![];
+        let mut islands = 0;
+        for start in 0..size * size {
+            if seen[start] || !self.points[start].escaped {
+                continue;
+            }
+            seen[start] = true;
+            stack.push(start);
+            let (mut reaches_edge, mut is_found) = (false, false);
+            while let Some(i) = stack.pop() {
+                let (x, y) = (i % size, i / size);
+                reaches_edge |= x == 0 || y == 0 || x == size - 1 || y == size - 1;
+                is_found |= found[i];
+                for (nx, ny) in neighbours(x, y, size) {
+                    let j = nx + size * ny;
+                    if !seen[j] && self.points[j].escaped {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+            if !reaches_edge && is_found {
+                islands += 1;
+            }
+        }
+        islands
+    }
+
+    // Series approximation and perturbation are both built on the Mandelbrot set's own formula.
+    pub(crate) fn is_mandelbrot(&self) -> bool {
+        self.julia.is_none() && self.formula == Formula::MANDELBROT
+    }
+
+    // With a tolerance set, iterate_as_required starts fresh points from a series approximation
+    // rather than from their locations.  Subsets inherit the setting.
+    pub fn set_series_tolerance(&mut self, tolerance: Option<f64>) {
+        self.series_tolerance = tolerance;
+    }
+
     fn generate_points(
         power_size: usize,
-        centre: Complex,
-        radius: Fix2x61,
-    ) -> Result<Vec<Point>, Error> {
+        centre: Complex<N>,
+        radius: N,
+        julia: Option<Complex<N>>,
+        formula: Formula,
+    ) -> Result<Vec<Point<N>>, Error> {
         assert!(power_size >= 2 && power_size < (size_of_val(&power_size) * 8));
         let size = 1 << power_size;
-        let mut points = vec![Point::ORIGIN; size * size];
+        let mut points = vec![Point::origin(); size * size];
 
         // d is half the distance between the points we'll sample.
         // Imagine our square area is made up of size ^ 2 smaller squares.  Our aim is to iterate
         // the middle of each of these smaller squares.
-        let d = (radius * Fix2x61::power_of_two(-(power_size as i8))?).truncate()?;
-        let d2 = (Fix2x61::two() * d).truncate()?;
-        let r_start = ((centre.r - radius)? + d)?;
-        let mut i: Result<Fix2x61, Error> = ((centre.i - radius)? + d).map_err(Into::into);
-        for each_i in 0..size - 1 {
+        let d = radius
+            .clone()
+            .widening_mul(N::power_of_two(-(power_size as i32))?)
+            .truncate()?;
+        let d2 = N::TWO.widening_mul(d.clone()).truncate()?;
+        let r_start = centre.r.try_sub(radius.clone())?.try_add(d.clone())?;
+        let mut i: Result<N, Error> = centre.i.try_sub(radius)?.try_add(d).map_err(Into::into);
+        for each_i in 0..size {
             let i_ = i.context(each_i)?;
-            let mut r: Result<Fix2x61, Error> = Ok(r_start);
-            for each_r in 0..size - 1 {
+            let mut r: Result<N, Error> = Ok(r_start.clone());
+            for each_r in 0..size {
                 let r_ = r.context(each_r)?;
-                let point = Point::from_parts(&r_, &i_);
+                let z = Complex::new(r_.clone(), i_.clone());
+                let mut point = match &julia {
+                    Some(c) => Point::julia(z.clone(), c.clone()),
+                    None => Point::with_formula(z.clone(), formula),
+                };
+                point.formula = formula;
+                if julia.is_none() && formula == Formula::MANDELBROT {
+                    point.period = known_period(&z);
+                }
                 points[each_r + size * each_i] = point;
-                r = (r_ + d2).with_context(|| format!("r + d: {:?} + {:?}", r_, d2))
+                r = r_
+                    .clone()
+                    .try_add(d2.clone())
+                    .with_context(|| format!("r + d: {:?} + {:?}", r_, d2))
             }
-            i = (i_ + d2).with_context(|| format!("i + d: {:?} + {:?}", i_, d2));
+            i = i_
+                .clone()
+                .try_add(d2.clone())
+                .with_context(|| format!("i + d: {:?} + {:?}", i_, d2));
         }
         Ok(points)
     }
@@ -112,7 +569,7 @@ impl Set {
     pub fn seen_escapes_to(&self) -> u64 {
         self.points
             .iter()
-            .max_by_key(|&p| if p.escaped { p.iterations } else { 0 })
+            .max_by_key(|&p| if p.finished() { p.iterations } else { 0 })
             .map(|p| p.iterations)
             .unwrap_or_default()
     }
@@ -124,6 +581,19 @@ impl Set {
     }
 
     pub fn iterate_as_required(&mut self, min_iter: u64, verbose: bool) -> Result<(), Error> {
+        if let Some(tolerance) = self.series_tolerance {
+            if self.points.iter().all(|p| p.iterations == 0) {
+                self.skip_with_series(tolerance, min_iter, verbose)?;
+            }
+        }
+
+        // Subdividing needs its limit up front, so we take the furthest flooding would go.
+        if self.strategy == Strategy::MarianiSilver {
+            return self.iterate_subdividing(min_iter * 2, verbose);
+        }
+
+        self.seed();
+
         //println!("Starting to iterate");
         let mut seen_escapes_up_to: u64 = min_iter;
         let mut new_candidates = true;
@@ -136,22 +606,21 @@ impl Set {
                     .with_context(|| format!("Iterating point {:?}", p.value()))
                     .unwrap()
             });
-            let size = 1 << self.power_size as i64;
-            for i in 0..size * size {
-                if self.points[i as usize].escaped {
-                    new_candidates |= update_if_in_range(&mut self.points, i + 1, size);
-                    new_candidates |= update_if_in_range(&mut self.points, i - 1, size);
-                    new_candidates |= update_if_in_range(&mut self.points, i + size + 1, size);
-                    new_candidates |= update_if_in_range(&mut self.points, i + size - 1, size);
-                    new_candidates |= update_if_in_range(&mut self.points, i - size + 1, size);
-                    new_candidates |= update_if_in_range(&mut self.points, i - size - 1, size);
+            let size = 1 << self.power_size;
+            for y in 0..size {
+                for x in 0..size {
+                    if self.points[x + size * y].finished() {
+                        new_candidates |= mark_neighbours(&mut self.points, x, y, size);
+                    }
                 }
             }
-            if let Some(m) =
-                self.points
-                    .iter()
-                    .max_by_key(|&p| if p.escaped { Some(p.iterations) } else { None })
-            {
+            if let Some(m) = self.points.iter().max_by_key(|&p| {
+                if p.finished() {
+                    Some(p.iterations)
+                } else {
+                    None
+                }
+            }) {
                 seen_escapes_up_to = m.iterations;
             }
         }
@@ -180,6 +649,14 @@ impl Set {
                 "Saw maximum {} iterations ({} candidates, {} not candidates) (break at {})",
                 maximum_escaped_iterations, candidates, not_candidates, seen_escapes_up_to,
             );
+            let (islands, from_border) = (self.islands(), self.islands_from_border());
+            println!(
+                "{:?} seeding found {} islands of escaping points away from the edge, {} more than \
+                 flooding from the edge alone",
+                self.seeding,
+                islands,
+                islands - from_border
+            );
         }
         Ok(())
     }
@@ -189,12 +666,235 @@ impl Set {
     }
 }
 
-fn update_if_in_range(new_points: &mut [Point], r: i64, size: i64) -> bool {
-    if r >= 0 && r < size * size {
-        let old_val = new_points[r as usize].escape_candidate;
-        new_points[r as usize].escape_candidate = true;
-        !old_val
-    } else {
-        false
+// The eight points around (x, y), or as many of them as are in the picture.
+fn neighbours(x: usize, y: usize, size: usize) -> impl Iterator<Item = (usize, usize)> {
+    (y.saturating_sub(1)..=min(y + 1, size - 1))
+        .flat_map(move |ny| (x.saturating_sub(1)..=min(x + 1, size - 1)).map(move |nx| (nx, ny)))
+        .filter(move |&n| n != (x, y))
+}
+
+// Makes candidates of the neighbours of (x, y), and says whether any of them weren't already.
+fn mark_neighbours<N>(points: &mut [Point<N>], x: usize, y: usize, size: usize) -> bool {
+    let mut marked = false;
+    for (nx, ny) in neighbours(x, y, size) {
+        let p = &mut points[nx + size * ny];
+        marked |= !p.escape_candidate;
+        p.escape_candidate = true;
+    }
+    marked
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use anyhow::Error;
+
+    use crate::complex::Complex;
+    use crate::double_double::DoubleDouble;
+    use crate::fix::fix2x61::Fix2x61;
+    use crate::formula::Formula;
+    use crate::number::MandelbrotNumber;
+    use crate::point::Point;
+    use crate::zoom_path::Quad;
+
+    use itertools::Itertools;
+
+    use super::{known_period, mark_neighbours, Seeding, Set};
+
+    // Every point we'd skip should never escape when iterated the long way, and any cycle it's
+    // found to settle into should be made up of the one we expected.  Also counts how many we
+    // skipped, and how many we could have but were too close to the edge to be sure of.
+    fn matches_brute_force<N: MandelbrotNumber>() -> Result<(usize, usize), Error> {
+        let (mut known, mut missed) = (0, 0);
+        for y in 0..64 {
+            for x in 0..64 {
+                let (r, i) = (-2.0 + 2.5 * x as f64 / 63.0, -1.25 + 2.5 * y as f64 / 63.0);
+                let c = Complex::new(N::from_f64(r)?, N::from_f64(i)?);
+                let mut p = Point::new(c.clone());
+                p.iterate_n(10_000)?;
+                match known_period(&c) {
+                    Some(period) => {
+                        known += 1;
+                        assert!(!p.escaped, "{}, {}", r, i);
+                        if let Some(found) = p.period {
+                            assert_eq!(found % period, 0, "{}, {}", r, i);
+                        }
+                    }
+                    None if matches!(p.period, Some(1 | 2)) => missed += 1,
+                    None => {}
+                }
+            }
+        }
+        Ok((known, missed))
+    }
+
+    #[test]
+    fn known_interior_matches_brute_force() -> Result<(), Error> {
+        let (known, missed) = matches_brute_force::<Fix2x61>()?;
+        assert!(known > 64 * 64 / 16, "{}", known);
+        assert_eq!(missed, 0);
+        assert_eq!(matches_brute_force::<f64>()?, (known, 0));
+        // Single precision needs a much wider margin.
+        let (known_f32, _) = matches_brute_force::<f32>()?;
+        assert!(
+            known_f32 <= known && known_f32 * 10 > known * 9,
+            "{}",
+            known_f32
+        );
+        Ok(())
+    }
+
+    fn skipped<N>(set: &Set<N>) -> usize {
+        set.points.iter().filter(|p| p.period.is_some()).count()
+    }
+
+    #[test]
+    fn skips_known_interior() -> Result<(), Error> {
+        let mut set: Set = Set::create(6, Complex::zero(), Fix2x61::try_from(2)?)?;
+        let known = skipped(&set);
+        assert!(known > 64 * 64 / 16, "{}", known);
+        set.iterate_as_required(100, false)?;
+        let untouched = set
+            .points
+            .iter()
+            .filter(|p| p.iterations == 0 && p.period.is_some());
+        assert_eq!(untouched.count(), known);
+        assert_eq!(skipped(&set.promote::<DoubleDouble>()?), known);
+
+        let mut ship: Set = Set::create(6, Complex::zero(), Fix2x61::try_from(2)?)?;
+        ship.set_formula(Formula::BurningShip);
+        assert_eq!(skipped(&ship), 0);
+        let julia = Set::create_julia(6, Complex::zero(), Fix2x61::try_from(2)?, Complex::zero())?;
+        assert_eq!(skipped(&julia), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn neighbours_stay_in_the_picture() {
+        let marked = |x, y| {
+            let mut points: Vec<Point> = vec![Point::origin(); 16];
+            assert!(mark_neighbours(&mut points, x, y, 4));
+            assert!(!mark_neighbours(&mut points, x, y, 4));
+            (0..16)
+                .filter(|&i| points[i].escape_candidate)
+                .map(|i| (i % 4, i / 4))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            marked(1, 1),
+            vec![
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (0, 1),
+                (2, 1),
+                (0, 2),
+                (1, 2),
+                (2, 2)
+            ]
+        );
+        assert_eq!(marked(3, 1), vec![(2, 0), (3, 0), (2, 1), (2, 2), (3, 2)]);
+        assert_eq!(marked(0, 3), vec![(0, 2), (1, 2), (1, 3)]);
+    }
+
+    // Flooding out from the edge should reach every point that escapes, so it should find them
+    // all escaping just where iterating every point does.
+    #[test]
+    fn flooding_misses_no_escapes() -> Result<(), Error> {
+        for &(r, i, radius) in &[(0.0, 0.0, 2.0), (-0.75, 0.1, 0.0625), (0.3, 0.5, 0.125)] {
+            let centre = Complex::new(Fix2x61::try_from(r)?, Fix2x61::try_from(i)?);
+            let radius = Fix2x61::try_from(radius)?;
+            let mut flooded: Set = Set::create(6, centre, radius)?;
+            flooded.iterate_as_required(100, false)?;
+            let limit = flooded
+                .points
+                .iter()
+                .map(|p| p.iterations)
+                .max()
+                .unwrap_or_default();
+
+            let mut brute_force: Set = Set::create(6, centre, radius)?;
+            brute_force
+                .points
+                .iter_mut()
+                .for_each(|p| p.escape_candidate = true);
+            brute_force.iterate_to(limit);
+            let missed = flooded
+                .points
+                .iter()
+                .zip(brute_force.points.iter())
+                .filter(|(f, b)| b.escaped && (!f.escaped || f.iterations != b.iterations))
+                .count();
+            assert_eq!(missed, 0, "{} {}", r, i);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn subsets_estimate_distances() -> Result<(), Error> {
+        let missing = |set: &Set| {
+            let escaped = set.points.iter().filter(|p| p.escaped);
+            assert!(escaped.clone().count() > 0);
+            escaped.filter(|p| p.distance().is_none()).count()
+        };
+        let mut set: Set = Set::create(6, Complex::zero(), Fix2x61::try_from(2)?)?;
+        set.estimate_distances();
+        set.iterate_as_required(100, false)?;
+        assert_eq!(missing(&set), 0);
+        let mut subset = set.subset(&Quad::TopLeft)?;
+        subset.iterate_as_required(100, false)?;
+        assert_eq!(missing(&subset), 0);
+        Ok(())
+    }
+
+    // In Magnet I's Julia set for i, escaping regions are scattered among points that neither
+    // escape nor converge, so many of them can't be reached from the edge.  Its orbits go well
+    // beyond the range of fixed point.
+    fn magnet(seeding: Seeding) -> Result<Set<f64>, Error> {
+        let mut set = Set::create_julia(6, Complex::new(0.0, 0.0), 1.0, Complex::new(0.0, 1.0))?;
+        set.set_formula(Formula::MagnetI);
+        set.set_seeding(seeding);
+        set.iterate_as_required(500, false)?;
+        Ok(set)
+    }
+
+    #[test]
+    fn seeding_finds_islands() -> Result<(), Error> {
+        let escaped = |set: &Set<f64>| set.points.iter().filter(|p| p.escaped).count();
+        let (border, full) = (magnet(Seeding::Border)?, magnet(Seeding::Full)?);
+        assert!(border.islands() < full.islands());
+        for seeding in [Seeding::Grid, Seeding::Random(0)] {
+            let set = magnet(seeding)?;
+            assert!(set.islands() <= full.islands(), "{:?}", seeding);
+            assert!(escaped(&set) > escaped(&border), "{:?}", seeding);
+            assert!(escaped(&set) < escaped(&full), "{:?}", seeding);
+        }
+        let candidates = |set: &Set<f64>| set.points.iter().filter(|p| p.escape_candidate).count();
+        assert_eq!(candidates(&full), 64 * 64);
+        assert_eq!(border.islands_from_border(), border.islands());
+        assert!(full.islands_from_border() < full.islands());
+        Ok(())
+    }
+
+    #[test]
+    fn random_probes_differ() -> Result<(), Error> {
+        let probes = |seeding| -> Result<Vec<usize>, Error> {
+            let mut set = Set::create(6, Complex::new(0.0, 0.0), 2.0)?;
+            set.set_seeding(seeding);
+            Ok(set.probes())
+        };
+        let first = probes(Seeding::Random(0))?;
+        assert_eq!(first.len(), 64);
+        assert_eq!(first.iter().unique().count(), 64);
+        assert_ne!(first, probes(Seeding::Random(1))?);
+
+        let mut set = Set::create(6, Complex::new(0.0, 0.0), 2.0)?;
+        set.set_seeding(Seeding::Random(0));
+        let (left, right) = (set.subset(&Quad::TopLeft)?, set.subset(&Quad::TopRight)?);
+        assert_ne!(left.probes(), first);
+        assert_ne!(left.probes(), right.probes());
+        assert_eq!("random:7".parse::<Seeding>()?, Seeding::Random(7));
+        Ok(())
     }
 }
@@ -9,6 +9,18 @@ pub enum Quad {
     BottomRight,
 }
 
+impl Quad {
+    // The quad on the other side of the horizontal midline.
+    pub fn flip_vertical(&self) -> Quad {
+        match self {
+            Quad::TopLeft => Quad::BottomLeft,
+            Quad::TopRight => Quad::BottomRight,
+            Quad::BottomLeft => Quad::TopLeft,
+            Quad::BottomRight => Quad::TopRight,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ZoomPath(pub Vec<Quad>);
 